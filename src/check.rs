@@ -6,6 +6,82 @@ pub(crate) fn check_config(config: &Config) -> Result<(), Error> {
     if config.gwas.is_empty() {
         return Err(Error::from("No GWAS specified."))
     }
+    if let Some(n_starts) = config.train.n_starts {
+        if n_starts == 0 {
+            return Err(Error::from("Number of starts needs to be positive."))
+        }
+        if n_starts > 1 && config.train.checkpoint.is_some() {
+            return Err(Error::from("Checkpoints are not supported with multiple starts."))
+        }
+    }
+    if let Some(bootstrap) = &config.train.bootstrap {
+        if bootstrap.n_replicates < 2 {
+            return Err(Error::from(format!(
+                "Number of bootstrap replicates needs to be at least 2, but is {}.",
+                bootstrap.n_replicates
+            )))
+        }
+        if let Some(confidence_level) = bootstrap.confidence_level {
+            if !(confidence_level > 0.0 && confidence_level < 1.0) {
+                return Err(Error::from(format!(
+                    "Confidence level needs to be between 0 and 1, but is {}.", confidence_level
+                )))
+            }
+        }
+    }
+    if let Some(n_chains) = config.train.n_chains {
+        if n_chains < 2 {
+            return Err(Error::from(format!(
+                "Number of chains needs to be at least 2, but is {}.", n_chains
+            )))
+        }
+    }
+    if let Some(e_quantiles) = &config.classify.e_quantiles {
+        for p in e_quantiles {
+            if !(*p > 0.0 && *p < 1.0) {
+                return Err(Error::from(format!(
+                    "E quantiles need to be between 0 and 1, but got {}.", p
+                )))
+            }
+        }
+    }
+    if let Some(param_draws) = &config.classify.param_draws {
+        if param_draws.trace_file.is_some() == param_draws.covariance_file.is_some() {
+            return Err(Error::from(
+                "Parameter draws need either a trace file or a covariance file, but not both."
+            ))
+        }
+        if param_draws.n_draws == Some(0) {
+            return Err(Error::from("Number of parameter draws needs to be positive."))
+        }
+    }
+    if let Some(adaptive) = &config.classify.adaptive {
+        if adaptive.n_samples_min > adaptive.n_samples_max {
+            return Err(Error::from(format!(
+                "Minimum number of samples ({}) is larger than maximum ({}).",
+                adaptive.n_samples_min, adaptive.n_samples_max
+            )))
+        }
+        if adaptive.block_size == Some(0) {
+            return Err(Error::from("Block size for adaptive sampling needs to be positive."))
+        }
+        if let Some(mcse_fraction) = adaptive.mcse_fraction {
+            if mcse_fraction.is_nan() || mcse_fraction <= 0.0 {
+                return Err(Error::from(format!(
+                    "MCSE fraction needs to be positive, but got {}.", mcse_fraction
+                )))
+            }
+        }
+    }
+    if let Some(models) = &config.classify.models {
+        for (i_model, model) in models.iter().enumerate() {
+            if models[..i_model].iter().any(|other| other.name == model.name) {
+                return Err(Error::from(format!(
+                    "Model name {} is used more than once.", model.name
+                )))
+            }
+        }
+    }
     Ok(())
 }
 
@@ -27,4 +103,53 @@ pub(crate) fn check_params(config: &Config, params: &Params) -> Result<(), Error
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::check::check_config;
+    use crate::options::config::Config;
+
+    fn config_with_bootstrap(bootstrap: &str) -> Config {
+        let toml_string = format!(r#"
+            [[gwas]]
+            name = "a"
+            file = "a.tsv"
+            [files]
+            params = "params.json"
+            [train]
+            ids_file = "ids.txt"
+            n_steps_burn_in = 10
+            n_samples_per_iteration = 10
+            n_iterations_per_round = 10
+            n_rounds = 1
+            normalize_mu_to_one = true
+            [train.bootstrap]
+            out_file = "bootstrap.tsv"
+            {}
+            [classify]
+            n_steps_burn_in = 10
+            n_samples = 10
+            out_file = "out.tsv"
+        "#, bootstrap);
+        toml::from_str(&toml_string).unwrap()
+    }
+
+    #[test]
+    fn bootstrap_needs_two_replicates() {
+        assert!(check_config(&config_with_bootstrap("n_replicates = 2")).is_ok());
+        assert!(check_config(&config_with_bootstrap("n_replicates = 1")).is_err());
+        assert!(check_config(&config_with_bootstrap("n_replicates = 0")).is_err());
+    }
+
+    #[test]
+    fn bootstrap_confidence_level_in_unit_interval() {
+        let with_level = |level: &str| {
+            config_with_bootstrap(&format!("n_replicates = 10\nconfidence_level = {}", level))
+        };
+        assert!(check_config(&with_level("0.9")).is_ok());
+        for level in ["0.0", "1.0", "-0.5", "1.5", "nan"] {
+            assert!(check_config(&with_level(level)).is_err());
+        }
+    }
+}
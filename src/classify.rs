@@ -1,9 +1,14 @@
 mod worker;
-mod exact;
+pub(crate) mod exact;
+mod models;
+mod param_draws;
+mod resume;
+pub(crate) mod merge;
 
 use std::cmp;
 use std::fs::File;
 use std::io::BufWriter;
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread::available_parallelism;
@@ -11,12 +16,25 @@ use crate::data::{GwasData, load_data, Meta};
 use crate::error::{Error, for_file};
 use crate::options::action::Action;
 use crate::options::config::{ClassifyConfig, Config};
-use crate::params::{Params, read_params_from_file};
+use crate::options::shard::Shard;
+use crate::params::{Params, read_params_from_file, write_params_with_seed_to_file};
+use crate::util::files::write_via_partial;
+use crate::util::rng::seed_or_random;
 use crate::util::threads::{InMessage, OutMessage, TaskQueueObserver, Threads, WorkerLauncher};
 use std::io::Write;
 use crate::check::check_params;
+use crate::classify::exact::{Contributions, Heterogeneity};
+use crate::classify::models::{load_models, Model, ModelClassification};
+use crate::classify::param_draws::{load_param_draws, ParamUncertainty};
+use crate::classify::resume::{read_saved_seed, read_temp_file, SavedEntries};
 use crate::classify::worker::classify_worker;
-use crate::sample::var_stats::SampledClassification;
+use crate::sample::var_stats::{EOptions, SampledClassification};
+
+mod defaults {
+    pub(crate) const E_QUANTILES: [f64; 3] = [0.025, 0.5, 0.975];
+    pub(crate) const ADAPTIVE_BLOCK_SIZE: usize = 100;
+    pub(crate) const ADAPTIVE_MCSE_FRACTION: f64 = 0.05;
+}
 
 #[derive(Clone)]
 pub(crate) enum MessageToWorker {
@@ -30,7 +48,22 @@ impl OutMessage for MessageToWorker {
 
 struct Classification {
     sampled: SampledClassification,
+    n_samples: usize,
+    seed: u64,
+    e_mcse: f64,
+    e_autocorrelation: f64,
     e_mean_calculated: f64,
+    e_std_calculated: f64,
+    log10_bayes_factor: f64,
+    heterogeneity: Heterogeneity,
+    shrinkages: Vec<f64>,
+    betas_observed: Vec<f64>,
+    ses_observed: Vec<f64>,
+    imputed: Vec<bool>,
+    contributions: Contributions,
+    models: Vec<ModelClassification>,
+    best_model: Option<String>,
+    param_uncertainty: Option<ParamUncertainty>,
 }
 
 pub(crate) struct MessageToCentral {
@@ -44,24 +77,33 @@ impl InMessage for MessageToCentral {
 
 struct Observer {
     meta: Meta,
-    var_ids: Arc<Vec<String>>,
+    config: ClassifyConfig,
+    var_ids: Vec<String>,
     writer: BufWriter<File>,
+    append: bool,
 }
 
 impl Observer {
-    fn new(var_ids: &Arc<Vec<String>>, file_name: &str, meta: Meta) -> Result<Observer, Error> {
-        let var_ids = var_ids.clone();
-        let writer =
-            BufWriter::new(for_file(file_name, File::create(file_name))?);
-        Ok(Observer { meta, var_ids, writer })
+    fn new(var_ids: Vec<String>, file_name: &str, meta: Meta, config: ClassifyConfig,
+           append: bool) -> Result<Observer, Error> {
+        let file =
+            if append {
+                for_file(file_name, File::options().append(true).open(file_name))?
+            } else {
+                for_file(file_name, File::create(file_name))?
+            };
+        let writer = BufWriter::new(file);
+        Ok(Observer { meta, config, var_ids, writer, append })
     }
 }
 
 impl TaskQueueObserver<MessageToCentral, MessageToWorker> for Observer {
     fn going_to_start_queue(&mut self) {
         println!("Starting to classify data points.");
-        if let Err(error) = write_header(&mut self.writer, &self.meta) {
-            println!("Cannot write temp file: {}", error)
+        if !self.append {
+            if let Err(error) = write_header(&mut self.writer, &self.meta, &self.config) {
+                println!("Cannot write temp file: {}", error)
+            }
         }
     }
 
@@ -73,8 +115,10 @@ impl TaskQueueObserver<MessageToCentral, MessageToWorker> for Observer {
 
     fn have_received(&mut self, in_message: &MessageToCentral, i_task: usize, _: usize) {
         let var_id = &self.var_ids[i_task];
+        //  Flushed after each entry, so a killed job loses at most a partial last line
         let io_result =
-            write_entry(&mut self.writer, var_id, &in_message.classification);
+            write_entry(&mut self.writer, var_id, &in_message.classification, f64::NAN)
+                .and_then(|_| Ok(self.writer.flush()?));
         if let Err(error) = io_result {
             println!("Cannot write temp file: {}", error)
         }
@@ -93,18 +137,43 @@ impl TaskQueueObserver<MessageToCentral, MessageToWorker> for Observer {
 struct ClassifyWorkerLauncher {
     data: Arc<GwasData>,
     params: Params,
+    models: Vec<Model>,
+    param_draws: Arc<Vec<Params>>,
+    seed: u64,
     config: ClassifyConfig,
 }
 
 impl WorkerLauncher<MessageToCentral, MessageToWorker> for ClassifyWorkerLauncher {
     fn launch(self, in_sender: Sender<MessageToCentral>, out_receiver: Receiver<MessageToWorker>,
               i_thread: usize) {
-        let ClassifyWorkerLauncher { data, params, config } = self;
-        classify_worker(&data, &params, config, in_sender, out_receiver, i_thread);
+        classify_worker(self, in_sender, out_receiver, i_thread);
     }
 }
 
-pub(crate) fn classify_or_check(config: &Config, dry: bool) -> Result<(), Error> {
+pub(crate) fn classify_or_check(config: &Config, dry: bool, resume: bool, shard: Option<Shard>)
+                                -> Result<(), Error> {
+    //  Each variant draws from its own stream, so the seed reproduces results also when
+    //  resuming or sharding. When resuming, the seed of the temp file is used.
+    let saved_seed =
+        if resume {
+            let out_file =
+                match shard {
+                    None => { config.classify.out_file.clone() }
+                    Some(shard) => { shard.out_file(&config.classify.out_file) }
+                };
+            read_saved_seed(&temp_out_file(&out_file))?
+        } else {
+            None
+        };
+    let seed =
+        match (config.classify.seed, saved_seed) {
+            (Some(seed), Some(saved_seed)) if seed != saved_seed => {
+                Err(Error::from(format!("Temp file was written with seed {}, but seed is {}, \
+                so cannot resume.", saved_seed, seed)))?
+            }
+            (seed, saved_seed) => { seed_or_random(seed.or(saved_seed)) }
+        };
+    println!("Random seed is {}", seed);
     let params = read_params_from_file(&config.files.params)?;
     check_params(config, &params)?;
     println!("Read from file mu = {}, tau = {}", params.mu, params.tau);
@@ -117,57 +186,265 @@ pub(crate) fn classify_or_check(config: &Config, dry: bool) -> Result<(), Error>
                 params
             }
         };
+    let param_draws =
+        match &config.classify.param_draws {
+            None => { Vec::new() }
+            Some(param_draws_config) => {
+                let param_draws = load_param_draws(param_draws_config, &params, seed)?;
+                match &config.classify.params_override {
+                    None => { param_draws }
+                    Some(overwrite) => {
+                        param_draws.into_iter().map(|draw| draw.plus_overwrite(overwrite))
+                            .collect()
+                    }
+                }
+            }
+        };
     let data = load_data(config, Action::Classify)?;
+    let models =
+        match &config.classify.models {
+            None => { Vec::new() }
+            Some(model_configs) => { load_models(model_configs, config, &data)? }
+        };
     if dry {
         println!("User picked dry run only, so doing nothing.")
     } else {
-        classify(data.gwas_data, params, config)?;
+        let mut classify_config = config.classify.clone();
+        classify_config.seed = Some(seed);
+        classify(data.gwas_data, params, models, param_draws, classify_config, resume, shard)?;
     }
     Ok(())
 }
 
-pub(crate) fn classify(data: GwasData, params: Params, config: &Config) -> Result<(), Error> {
+pub(crate) fn classify(data: GwasData, params: Params, models: Vec<Model>,
+                       param_draws: Vec<Params>, mut config: ClassifyConfig, resume: bool,
+                       shard: Option<Shard>) -> Result<(), Error> {
     let data = Arc::new(data);
     let n_threads = cmp::max(available_parallelism()?.get(), 3);
-    let config = config.classify.clone();
-    let launcher = ClassifyWorkerLauncher { data: data.clone(), params, config: config.clone() };
+    let seed = seed_or_random(config.seed);
+    let range =
+        match shard {
+            None => { 0..data.meta.n_data_points() }
+            Some(shard) => {
+                config.out_file = shard.out_file(&config.out_file);
+                let range = shard.range(data.meta.n_data_points());
+                println!("Shard {} of {} has data points {} to {}, written to {}. Its lfsr q \
+                values are for this shard only, run merge-classify to combine shards.",
+                         shard.i_shard, shard.n_shards, range.start, range.end, config.out_file);
+                range
+            }
+        };
+    if shard.is_some() {
+        //  For merge-classify to check that all shards used the same params and seed
+        let params_file = shard_params_file(&config.out_file);
+        write_params_with_seed_to_file(&params, seed, &params_file)?;
+    }
+    let param_draws = Arc::new(param_draws);
+    let launcher =
+        ClassifyWorkerLauncher {
+            data: data.clone(), params, models, param_draws, seed, config: config.clone()
+        };
     let threads = Threads::new(launcher, n_threads);
     let meta = &data.meta;
-    let out_messages =
-        (0..meta.n_data_points()).map(MessageToWorker::DataPoint);
-    let temp_out_file = format!("{}_tmp", config.out_file);
+    let temp_out_file = temp_out_file(&config.out_file);
+    let append = resume && Path::new(&temp_out_file).exists();
+    let saved_entries =
+        if resume {
+            read_temp_file(&temp_out_file, &header_cols(meta, &config))?
+        } else {
+            SavedEntries::new()
+        };
+    let i_data_points: Vec<usize> =
+        range.clone().filter(|&i_data_point| {
+            !saved_entries.contains(&meta.var_ids[i_data_point])
+        }).collect();
+    if i_data_points.len() + saved_entries.n_entries() != range.len() {
+        Err(Error::from(format!("Temp file {} contains variants not in the data, so cannot \
+        resume.", temp_out_file)))?;
+    }
+    let var_ids_todo: Vec<String> =
+        i_data_points.iter().map(|&i_data_point| meta.var_ids[i_data_point].clone()).collect();
+    let out_messages = i_data_points.iter().cloned().map(MessageToWorker::DataPoint);
     let mut observer =
-        Observer::new(&meta.var_ids, &temp_out_file, meta.clone())?;
+        Observer::new(var_ids_todo, &temp_out_file, meta.clone(), config.clone(), append)?;
     let in_messages = threads.task_queue(out_messages, &mut observer)?;
-    let classifications: Vec<Classification> =
-        in_messages.into_iter().map(|in_message| in_message.classification).collect();
-    write_out_file(&config.out_file, meta, &classifications)?;
+    let mut classifications =
+        in_messages.into_iter().map(|in_message| in_message.classification);
+    let var_ids = &meta.var_ids[range];
+    let entries: Vec<Entry> =
+        var_ids.iter().map(|var_id| {
+            match saved_entries.entries.get(var_id) {
+                Some(values) => { Entry::Saved(values.clone()) }
+                None => { Entry::Fresh(Box::new(classifications.next().unwrap())) }
+            }
+        }).collect();
+    write_out_file(&config.out_file, meta, var_ids, &config, &entries)?;
     Ok(())
 }
 
-fn write_out_file(file: &str, meta: &Meta, classifications: &[Classification])
-                  -> Result<(), Error> {
-    let mut writer = BufWriter::new(for_file(file, File::create(file))?);
-    write_header(&mut writer, meta)?;
-    for (id, classification)
-    in meta.var_ids.iter().zip(classifications.iter()) {
-        write_entry(&mut writer, id, classification)?;
+fn temp_out_file(out_file: &str) -> String { format!("{}_tmp", out_file) }
+
+fn shard_params_file(out_file: &str) -> String { format!("{}_params", out_file) }
+
+pub(crate) fn e_options(config: &ClassifyConfig) -> EOptions {
+    let quantile_probs = config.e_quantiles.clone().unwrap_or(defaults::E_QUANTILES.to_vec());
+    let threshold = config.e_threshold;
+    EOptions { quantile_probs, threshold }
+}
+
+//  A variant classified in this run, or read back from the temp file of an earlier run
+enum Entry {
+    Fresh(Box<Classification>),
+    Saved(Vec<String>),
+}
+
+fn calculate_lfsr_q_values(lfsrs: &[f64]) -> Vec<f64> {
+    //  Mean lfsr of all variants ranked at or before this one by lfsr, i.e. the expected
+    //  proportion of false signs when following up on all of them.
+    let mut indices: Vec<usize> = (0..lfsrs.len()).collect();
+    indices.sort_by(|&i, &j| lfsrs[i].total_cmp(&lfsrs[j]));
+    let mut q_values: Vec<f64> = vec![f64::NAN; lfsrs.len()];
+    let mut lfsr_sum: f64 = 0.0;
+    for (rank, &i) in indices.iter().enumerate() {
+        lfsr_sum += lfsrs[i];
+        q_values[i] = lfsr_sum / ((rank + 1) as f64);
     }
-    Ok(())
+    q_values
+}
+
+fn write_out_file(file: &str, meta: &Meta, var_ids: &[String], config: &ClassifyConfig,
+                  entries: &[Entry]) -> Result<(), Error> {
+    let cols = header_cols(meta, config);
+    let i_col_lfsr = cols.iter().position(|col| col == "lfsr").unwrap();
+    let i_col_lfsr_q = cols.iter().position(|col| col == "lfsr_q").unwrap();
+    let lfsrs =
+        entries.iter().map(|entry| {
+            match entry {
+                Entry::Fresh(classification) => { Ok(classification.sampled.lfsr) }
+                Entry::Saved(values) => { values[i_col_lfsr].parse::<f64>() }
+            }
+        }).collect::<Result<Vec<f64>, _>>()?;
+    let q_values = calculate_lfsr_q_values(&lfsrs);
+    write_via_partial(file, |writer| {
+        write_header(writer, meta, config)?;
+        for ((id, entry), q_value) in var_ids.iter().zip(entries.iter()).zip(q_values) {
+            match entry {
+                Entry::Fresh(classification) => {
+                    write_entry(writer, id, classification, q_value)?;
+                }
+                Entry::Saved(values) => {
+                    let mut values = values.clone();
+                    values[i_col_lfsr_q] = q_value.to_string();
+                    writeln!(writer, "{}", values.join("\t"))?;
+                }
+            }
+        }
+        Ok(())
+    })
 }
 
-fn write_header(writer: &mut BufWriter<File>, meta: &Meta) -> Result<(), Error> {
-    let traits_part = meta.trait_names.join("\t");
-    writeln!(writer, "id\te_mean_samp\te_std_samp\te_mean_calc\t{}", traits_part)?;
+fn write_header(writer: &mut BufWriter<File>, meta: &Meta, config: &ClassifyConfig)
+                -> Result<(), Error> {
+    writeln!(writer, "{}", header_cols(meta, config).join("\t"))?;
     Ok(())
 }
 
-fn write_entry(writer: &mut BufWriter<File>, id: &str, classification: &Classification)
-               -> Result<(), Error> {
-    let Classification { sampled, e_mean_calculated } = classification;
-    let SampledClassification { e_mean, e_std, t_means } = sampled;
-    let t_means_part =
-        t_means.iter().map(|f| f.to_string()).collect::<Vec<_>>().join("\t");
-    writeln!(writer, "{}\t{}\t{}\t{}\t{}", id, e_mean, e_std, e_mean_calculated, t_means_part)?;
+fn header_cols(meta: &Meta, config: &ClassifyConfig) -> Vec<String> {
+    let mut cols: Vec<String> =
+        ["id", "e_mean_samp", "e_std_samp", "e_mcse_samp", "e_acf1_samp", "n_samples", "seed"]
+            .map(String::from).to_vec();
+    let e_options = e_options(config);
+    cols.extend(e_options.quantile_probs.iter().map(|p| format!("e_q_{}", p)));
+    cols.extend(["p_pos", "p_neg"].map(String::from));
+    if let Some(threshold) = e_options.threshold {
+        cols.push(format!("p_abs_gt_{}", threshold));
+    }
+    cols.extend(["lfsr", "lfsr_q"].map(String::from));
+    cols.extend(["e_mean_calc", "e_std_calc", "log10_bf", "het_chi2", "het_p"]
+        .map(String::from));
+    for prefix in ["resid", "t_mean", "t_sd", "shrink", "beta", "se"] {
+        cols.extend(meta.trait_names.iter()
+            .map(|trait_name| format!("{}_{}", prefix, trait_name)));
+    }
+    cols.extend(meta.trait_names.iter().map(|trait_name| format!("imputed_{}", trait_name)));
+    cols.push("weight_frac_prior".to_string());
+    cols.extend(meta.trait_names.iter().map(|trait_name| format!("weight_frac_{}", trait_name)));
+    cols.push("contrib_frac_prior".to_string());
+    cols.extend(meta.trait_names.iter().map(|trait_name| format!("contrib_frac_{}", trait_name)));
+    if let Some(models) = config.models.as_ref().filter(|models| !models.is_empty()) {
+        for model in models {
+            for prefix in ["e_mean", "e_std", "prob"] {
+                cols.push(format!("{}_{}", prefix, model.name));
+            }
+        }
+        cols.push("best_model".to_string());
+    }
+    if config.param_draws.is_some() {
+        cols.extend(["e_mean_draws", "e_std_draws", "e_var_data", "e_var_params",
+            "frac_var_params"].map(String::from));
+        cols.extend(e_options.quantile_probs.iter().map(|p| format!("e_q_{}_draws", p)));
+        cols.extend(["p_pos_draws", "p_neg_draws"].map(String::from));
+        if let Some(threshold) = e_options.threshold {
+            cols.push(format!("p_abs_gt_{}_draws", threshold));
+        }
+        cols.push("lfsr_draws".to_string());
+    }
+    cols
+}
+
+fn write_entry(writer: &mut BufWriter<File>, id: &str, classification: &Classification,
+               lfsr_q_value: f64) -> Result<(), Error> {
+    let Classification {
+        sampled, n_samples, seed, e_mcse, e_autocorrelation, e_mean_calculated,
+        e_std_calculated, log10_bayes_factor, heterogeneity, shrinkages, betas_observed,
+        ses_observed, imputed, contributions, models, best_model, param_uncertainty
+    } = classification;
+    let SampledClassification {
+        e_mean, e_std, e_quantiles, p_pos, p_neg, p_above_threshold, lfsr, t_means, t_stds
+    } = sampled;
+    let Heterogeneity { chi_squared, p_value, residuals } = heterogeneity;
+    let mut values: Vec<String> =
+        vec![id.to_string(), e_mean.to_string(), e_std.to_string(), e_mcse.to_string(),
+             e_autocorrelation.to_string(), n_samples.to_string(), seed.to_string()];
+    values.extend(e_quantiles.iter().map(|f| f.to_string()));
+    values.extend([p_pos, p_neg].map(|f| f.to_string()));
+    if let Some(p_above_threshold) = p_above_threshold {
+        values.push(p_above_threshold.to_string());
+    }
+    values.extend([lfsr, &lfsr_q_value].map(|f| f.to_string()));
+    values.extend([e_mean_calculated, e_std_calculated, log10_bayes_factor, chi_squared, p_value]
+        .map(|f| f.to_string()));
+    for per_trait in [residuals, t_means, t_stds, shrinkages, betas_observed, ses_observed] {
+        values.extend(per_trait.iter().map(|f| f.to_string()));
+    }
+    values.extend(imputed.iter().map(|&is_imputed| u8::from(is_imputed).to_string()));
+    let Contributions {
+        weight_frac_prior, weight_fracs, contrib_frac_prior, contrib_fracs
+    } = contributions;
+    values.push(weight_frac_prior.to_string());
+    values.extend(weight_fracs.iter().map(|f| f.to_string()));
+    values.push(contrib_frac_prior.to_string());
+    values.extend(contrib_fracs.iter().map(|f| f.to_string()));
+    for model in models {
+        values.extend([model.e_mean, model.e_std, model.prob].map(|f| f.to_string()));
+    }
+    if let Some(best_model) = best_model {
+        values.push(best_model.clone());
+    }
+    if let Some(ParamUncertainty {
+        e_mean, e_std, e_var_data, e_var_params, e_quantiles, p_pos, p_neg, p_above_threshold,
+        lfsr
+    }) = param_uncertainty {
+        let frac_var_params = e_var_params / (e_var_data + e_var_params);
+        values.extend([e_mean, e_std, e_var_data, e_var_params, &frac_var_params]
+            .map(|f| f.to_string()));
+        values.extend(e_quantiles.iter().map(|f| f.to_string()));
+        values.extend([p_pos, p_neg].map(|f| f.to_string()));
+        if let Some(p_above_threshold) = p_above_threshold {
+            values.push(p_above_threshold.to_string());
+        }
+        values.push(lfsr.to_string());
+    }
+    writeln!(writer, "{}", values.join("\t"))?;
     Ok(())
-}
\ No newline at end of file
+}
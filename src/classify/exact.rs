@@ -1,17 +1,128 @@
+use crate::math::special::{chi_squared_survival, normal_cdf, normal_quantile};
 use crate::params::Params;
+use crate::sample::conditionals::{calculate_e_posterior, calculate_t_conditional, EPosterior,
+                                  TConditional};
+use crate::sample::var_stats::{EOptions, SampledClassification};
 
-pub(crate) fn calculate_mu(params: &Params, betas: &[f64], ses: &[f64]) -> f64 {
+//  Shares of the posterior precision and of the posterior mean numerator, for the prior and
+//  each trait. Each set of fractions sums to one.
+pub(crate) struct Contributions {
+    pub(crate) weight_frac_prior: f64,
+    pub(crate) weight_fracs: Vec<f64>,
+    pub(crate) contrib_frac_prior: f64,
+    pub(crate) contrib_fracs: Vec<f64>,
+}
+
+pub(crate) fn calculate_contributions(params: &Params, betas: &[f64], ses: &[f64])
+                                      -> Contributions {
     let tau2 = params.tau.powi(2);
-    let numerator: f64 =
-        params.betas.iter().zip(params.sigmas.iter()).zip(betas.iter())
-            .zip(ses.iter())
-            .map(|(((&beta, &sigma), &o), &se)| {
-                beta * o / (sigma.powi(2) + se.powi(2))
-            }).sum::<f64>() + params.mu / tau2;
-    let denominator: f64 =
-        params.betas.iter().zip(params.sigmas.iter()).zip(ses.iter())
-            .map(|((&beta, &sigma), &se)| {
-                beta.powi(2) / (sigma.powi(2) + se.powi(2))
-            }).sum::<f64>() + 1.0 / tau2;
-    numerator / denominator
-}
\ No newline at end of file
+    let weight_prior = 1.0 / tau2;
+    let contrib_prior = params.mu / tau2;
+    let ds: Vec<f64> =
+        params.sigmas.iter().zip(ses.iter()).map(|(sigma, se)| sigma.powi(2) + se.powi(2))
+            .collect();
+    let weights: Vec<f64> =
+        params.betas.iter().zip(ds.iter()).map(|(beta, d)| beta.powi(2) / d).collect();
+    let contribs: Vec<f64> =
+        params.betas.iter().zip(betas.iter()).zip(ds.iter()).map(|((beta, o), d)| beta * o / d)
+            .collect();
+    let precision = weight_prior + weights.iter().sum::<f64>();
+    let numerator = contrib_prior + contribs.iter().sum::<f64>();
+    let weight_frac_prior = weight_prior / precision;
+    let weight_fracs: Vec<f64> = weights.iter().map(|weight| weight / precision).collect();
+    let contrib_frac_prior = contrib_prior / numerator;
+    let contrib_fracs: Vec<f64> =
+        contribs.iter().map(|contrib| contrib / numerator).collect();
+    Contributions { weight_frac_prior, weight_fracs, contrib_frac_prior, contrib_fracs }
+}
+
+pub(crate) fn calculate_exact_classification(params: &Params, betas: &[f64], ses: &[f64],
+                                             e_options: &EOptions) -> SampledClassification {
+    let e_posterior = calculate_e_posterior(params, betas, ses);
+    let e_mean = e_posterior.mean;
+    let e_std = e_posterior.variance.sqrt();
+    let e_quantiles: Vec<f64> =
+        e_options.quantile_probs.iter().map(|&p| e_mean + e_std * normal_quantile(p)).collect();
+    let p_pos = normal_cdf(e_mean / e_std);
+    let p_neg = 1.0 - p_pos;
+    let p_above_threshold =
+        e_options.threshold.map(|threshold| {
+            normal_cdf((-threshold - e_mean) / e_std) + normal_cdf((e_mean - threshold) / e_std)
+        });
+    let lfsr = p_pos.min(p_neg);
+    let t_conditionals: Vec<TConditional> =
+        params.betas.iter().zip(params.sigmas.iter()).zip(betas.iter()).zip(ses.iter())
+            .map(|(((&beta, &sigma), &o), &se)| calculate_t_conditional(beta, sigma, o, se))
+            .collect();
+    let t_means: Vec<f64> =
+        t_conditionals.iter().map(|t_conditional| {
+            t_conditional.coeff_e * e_mean + t_conditional.offset
+        }).collect();
+    let t_stds: Vec<f64> =
+        t_conditionals.iter().map(|t_conditional| {
+            (t_conditional.coeff_e.powi(2) * e_posterior.variance + t_conditional.variance).sqrt()
+        }).collect();
+    SampledClassification {
+        e_mean, e_std, e_quantiles, p_pos, p_neg, p_above_threshold, lfsr, t_means, t_stds
+    }
+}
+
+pub(crate) struct TPrediction {
+    pub(crate) mean: f64,
+    pub(crate) variance: f64,
+}
+
+//  Predictive distribution of T for a trait the variant has no observation for.
+pub(crate) fn predict_t(beta: f64, sigma: f64, e_posterior: &EPosterior) -> TPrediction {
+    let mean = beta * e_posterior.mean;
+    let variance = beta.powi(2) * e_posterior.variance + sigma.powi(2);
+    TPrediction { mean, variance }
+}
+
+//  Fraction by which the observed effect is pulled towards the model prediction beta*E.
+pub(crate) fn calculate_shrinkages(params: &Params, ses: &[f64]) -> Vec<f64> {
+    params.sigmas.iter().zip(ses.iter()).map(|(sigma, se)| {
+        let var_o = se.powi(2);
+        var_o / (sigma.powi(2) + var_o)
+    }).collect()
+}
+
+pub(crate) struct Heterogeneity {
+    pub(crate) chi_squared: f64,
+    pub(crate) p_value: f64,
+    pub(crate) residuals: Vec<f64>,
+}
+
+pub(crate) fn calculate_heterogeneity(params: &Params, betas: &[f64], ses: &[f64])
+                                      -> Heterogeneity {
+    //  Cochran-style test of the observed betas against beta*E + noise, with E at its
+    //  generalized least squares estimate, which under the model leaves a chi-squared
+    //  statistic with one degree of freedom less than the number of traits.
+    let vars: Vec<f64> =
+        params.sigmas.iter().zip(ses.iter()).map(|(&sigma, &se)| {
+            sigma.powi(2) + se.powi(2)
+        }).collect();
+    let info: f64 =
+        params.betas.iter().zip(vars.iter()).map(|(&beta, &var)| beta.powi(2) / var).sum();
+    let e_hat: f64 =
+        params.betas.iter().zip(vars.iter()).zip(betas.iter()).map(|((&beta, &var), &o)| {
+            beta * o / var
+        }).sum::<f64>() / info;
+    let n_degrees_of_freedom = betas.len() as f64 - 1.0;
+    if n_degrees_of_freedom < 1.0 || info <= 0.0 {
+        let chi_squared = f64::NAN;
+        let p_value = f64::NAN;
+        let residuals = vec![f64::NAN; betas.len()];
+        return Heterogeneity { chi_squared, p_value, residuals }
+    }
+    let mut chi_squared: f64 = 0.0;
+    let mut residuals: Vec<f64> = Vec::with_capacity(betas.len());
+    for ((&beta, &var), &o) in params.betas.iter().zip(vars.iter()).zip(betas.iter()) {
+        let residual = o - beta * e_hat;
+        chi_squared += residual.powi(2) / var;
+        let leverage = beta.powi(2) / var / info;
+        residuals.push(residual / (var * (1.0 - leverage)).sqrt());
+    }
+    let p_value = chi_squared_survival(chi_squared, n_degrees_of_freedom);
+    Heterogeneity { chi_squared, p_value, residuals }
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use crate::check::check_config;
+use crate::classify::{Entry, header_cols, shard_params_file, write_out_file};
+use crate::data::load_data;
+use crate::error::{Error, for_file};
+use crate::options::action::Action;
+use crate::options::cli::MergeClassifyOptions;
+use crate::options::config::load_config;
+use crate::options::shard::Shard;
+use crate::params::{Params, read_params_with_seed_from_file};
+
+pub(crate) fn merge_classify(options: &MergeClassifyOptions) -> Result<(), Error> {
+    let config = load_config(&options.config_file)?;
+    check_config(&config)?;
+    let data = load_data(&config, Action::Classify)?;
+    let meta = &data.gwas_data.meta;
+    let config = config.classify;
+    let cols = header_cols(meta, &config);
+    let i_col_seed = cols.iter().position(|col| col == "seed").unwrap();
+    let mut values_by_id: HashMap<String, Vec<String>> = HashMap::new();
+    let mut params_and_seed_first: Option<(Params, u64)> = None;
+    for i_shard in 1..=options.n_shards {
+        let shard = Shard::new(i_shard, options.n_shards)?;
+        let file = shard.out_file(&config.out_file);
+        let params_file = shard_params_file(&file);
+        let (params, seed) =
+            match read_params_with_seed_from_file(&params_file)? {
+                (params, Some(seed)) => { (params, seed) }
+                (_, None) => {
+                    Err(Error::from(format!("No seed in {}.", params_file)))?
+                }
+            };
+        match &params_and_seed_first {
+            None => { params_and_seed_first = Some((params, seed)) }
+            Some((params_first, seed_first)) => {
+                if seed != *seed_first {
+                    Err(Error::from(format!("Shard {} used seed {}, but shard 1 used {}.", file,
+                                            seed, seed_first)))?;
+                }
+                if params != *params_first {
+                    Err(Error::from(format!("Shard {} used other params than shard 1.",
+                                            file)))?;
+                }
+            }
+        }
+        let seed_string = seed.to_string();
+        let content = for_file(&file, read_to_string(&file))?;
+        let mut lines = content.lines();
+        if lines.next().unwrap_or("").split('\t').ne(cols.iter().map(|col| col.as_str())) {
+            Err(Error::from(format!("Header of {} does not match the config.", file)))?;
+        }
+        let mut n_entries: usize = 0;
+        for line in lines {
+            let values: Vec<String> = line.split('\t').map(|value| value.to_string()).collect();
+            if values.len() != cols.len() {
+                Err(Error::from(format!("Line of {} for {} has {} columns, but header has {}.",
+                                        file, values[0], values.len(), cols.len())))?;
+            }
+            if values[i_col_seed] != seed_string {
+                Err(Error::from(format!("Line of {} for {} has seed {}, but shard used {}.",
+                                        file, values[0], values[i_col_seed], seed)))?;
+            }
+            if let Some(values) = values_by_id.insert(values[0].clone(), values) {
+                Err(Error::from(format!("Variant {} is in more than one shard.", values[0])))?;
+            }
+            n_entries += 1;
+        }
+        let n_entries_expected = shard.range(meta.n_data_points()).len();
+        if n_entries != n_entries_expected {
+            Err(Error::from(format!("Shard {} has {} variants, but should have {}.", file,
+                                    n_entries, n_entries_expected)))?;
+        }
+        println!("Read {} variants from {}.", n_entries, file);
+    }
+    let entries =
+        meta.var_ids.iter().map(|var_id| {
+            values_by_id.remove(var_id).map(Entry::Saved).ok_or_else(|| {
+                Error::from(format!("Variant {} is missing from all shards.", var_id))
+            })
+        }).collect::<Result<Vec<Entry>, Error>>()?;
+    write_out_file(&config.out_file, meta, &meta.var_ids, &config, &entries)?;
+    println!("Wrote {} variants to {}.", entries.len(), config.out_file);
+    Ok(())
+}
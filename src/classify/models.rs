@@ -0,0 +1,107 @@
+use crate::sample::conditionals::calculate_e_posterior;
+use crate::data::{GwasData, load_data, LoadedData};
+use crate::error::Error;
+use crate::likelihood::compare::fit_unloaded_sigma;
+use crate::likelihood::variant_log_likelihood;
+use crate::options::action::Action;
+use crate::options::config::{Config, ModelConfig};
+use crate::params::{Params, read_params_from_file};
+
+#[derive(Clone)]
+pub(crate) struct Model {
+    pub(crate) name: String,
+    pub(crate) params: Params,
+}
+
+pub(crate) struct ModelClassification {
+    pub(crate) e_mean: f64,
+    pub(crate) e_std: f64,
+    pub(crate) log_likelihood: f64,
+    pub(crate) prob: f64,
+}
+
+pub(crate) fn load_models(model_configs: &[ModelConfig], config: &Config, data: &LoadedData)
+                          -> Result<Vec<Model>, Error> {
+    //  Models are evaluated on all loaded traits, so their likelihoods are comparable. Traits
+    //  not in a model do not load on E and get a sigma fitted to the training variants, which
+    //  are only loaded if needed.
+    let trait_names = data.gwas_data.meta.trait_names.clone();
+    let mut sigmas_unloaded: Vec<Option<f64>> = vec![None; trait_names.len()];
+    let mut data_train: Option<LoadedData> = None;
+    let mut models: Vec<Model> = Vec::with_capacity(model_configs.len());
+    for model_config in model_configs {
+        //  The params override applies to each model as it does to single-model params
+        let params =
+            match &config.classify.params_override {
+                None => { read_params_from_file(&model_config.params_file)? }
+                Some(overwrite) => {
+                    read_params_from_file(&model_config.params_file)?.plus_overwrite(overwrite)
+                }
+            };
+        let mut params_extended = params.extended_to(trait_names.clone())?;
+        for (i_trait, trait_name) in trait_names.iter().enumerate() {
+            if params.trait_names.contains(trait_name) {
+                continue
+            }
+            let sigma =
+                match sigmas_unloaded[i_trait] {
+                    Some(sigma) => { sigma }
+                    None => {
+                        if data_train.is_none() {
+                            println!("Loading training variants to fit sigmas of traits not \
+                            in all models.");
+                            data_train = Some(load_data(config, Action::Train)?);
+                        }
+                        let data_train = data_train.as_ref().unwrap();
+                        let sigma =
+                            fit_unloaded_sigma(&data_train.gwas_data, &data_train.weights,
+                                               i_trait);
+                        sigmas_unloaded[i_trait] = Some(sigma);
+                        sigma
+                    }
+                };
+            params_extended.sigmas[i_trait] = sigma;
+        }
+        println!("Model {} from {}: mu = {}, tau = {}", model_config.name,
+                 model_config.params_file, params.mu, params.tau);
+        let name = model_config.name.clone();
+        models.push(Model { name, params: params_extended })
+    }
+    Ok(models)
+}
+
+pub(crate) fn classify_models(models: &[Model], data: &GwasData, is_col: &[usize])
+                              -> Vec<ModelClassification> {
+    let betas = &data.betas[0];
+    let ses = &data.ses[0];
+    let mut model_classifications: Vec<ModelClassification> =
+        models.iter().map(|model| {
+            let params = model.params.reduce_to(data.meta.trait_names.clone(), is_col);
+            let e_posterior = calculate_e_posterior(&params, betas, ses);
+            let e_mean = e_posterior.mean;
+            let e_std = e_posterior.variance.sqrt();
+            let log_likelihood = variant_log_likelihood(&params, betas, ses);
+            ModelClassification { e_mean, e_std, log_likelihood, prob: f64::NAN }
+        }).collect();
+    //  Posterior model probabilities under equal prior odds
+    let log_likelihood_max =
+        model_classifications.iter().map(|model_classification| {
+            model_classification.log_likelihood
+        }).fold(f64::NEG_INFINITY, f64::max);
+    let prob_sum: f64 =
+        model_classifications.iter().map(|model_classification| {
+            (model_classification.log_likelihood - log_likelihood_max).exp()
+        }).sum();
+    for model_classification in model_classifications.iter_mut() {
+        model_classification.prob =
+            (model_classification.log_likelihood - log_likelihood_max).exp() / prob_sum;
+    }
+    model_classifications
+}
+
+pub(crate) fn best_model<'a>(models: &'a [Model],
+                             model_classifications: &[ModelClassification]) -> Option<&'a str> {
+    models.iter().zip(model_classifications.iter())
+        .max_by(|(_, a), (_, b)| a.prob.total_cmp(&b.prob))
+        .map(|(model, _)| model.name.as_str())
+}
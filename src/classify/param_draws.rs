@@ -0,0 +1,166 @@
+use std::fs::read_to_string;
+use rand_distr::{Distribution, StandardNormal};
+use crate::sample::conditionals::{calculate_e_posterior, EPosterior};
+use crate::data::GwasData;
+use crate::error::{Error, for_file};
+use crate::math::matrix::Matrix;
+use crate::math::special::normal_cdf;
+use crate::options::config::ParamDrawsConfig;
+use crate::params::{ParamIndex, Params};
+use crate::sample::var_stats::EOptions;
+use crate::sample::trace_file::read_param_trace_file;
+use crate::util::rng::{new_rng, RngStream};
+
+mod defaults {
+    pub(crate) const N_DRAWS: usize = 100;
+    pub(crate) const N_BISECTIONS: usize = 100;
+}
+
+//  E posterior integrated over parameter draws, a mixture of the Gaussian posteriors of the
+//  draws. The law of total variance splits the variance into the mean of the per-draw variances
+//  (data) and the variance of the per-draw means (parameters).
+pub(crate) struct ParamUncertainty {
+    pub(crate) e_mean: f64,
+    pub(crate) e_std: f64,
+    pub(crate) e_var_data: f64,
+    pub(crate) e_var_params: f64,
+    pub(crate) e_quantiles: Vec<f64>,
+    pub(crate) p_pos: f64,
+    pub(crate) p_neg: f64,
+    pub(crate) p_above_threshold: Option<f64>,
+    pub(crate) lfsr: f64,
+}
+
+//  The intended trace file is the bootstrap replicates file of training. A training params trace
+//  has one point estimate per round, including rounds before convergence, and is not a sample
+//  of the parameter posterior. Draws from a trace are rescaled to the E scale of the params.
+pub(crate) fn load_param_draws(config: &ParamDrawsConfig, params: &Params, seed: u64)
+                               -> Result<Vec<Params>, Error> {
+    let draws =
+        match (&config.trace_file, &config.covariance_file) {
+            (Some(trace_file), _) => {
+                let draws: Vec<Params> =
+                    read_param_trace_file(trace_file, params.trait_names.clone())?.iter()
+                        .map(|draw| draw.rescaled_to_mu(params.mu)).collect();
+                if draws.is_empty() {
+                    Err(Error::from(format!("No parameter draws in {}.", trace_file)))?;
+                }
+                match config.n_draws {
+                    Some(n_draws) if n_draws < draws.len() => {
+                        //  Evenly spaced, to thin out autocorrelated samples
+                        (0..n_draws).map(|i| draws[i * draws.len() / n_draws].clone()).collect()
+                    }
+                    _ => { draws }
+                }
+            }
+            (None, Some(covariance_file)) => {
+                let covariance = read_covariance_file(covariance_file, params.n_traits())?;
+                let n_draws = config.n_draws.unwrap_or(defaults::N_DRAWS);
+                draw_gaussian(params, &covariance, n_draws, seed)?
+            }
+            (None, None) => {
+                Err(Error::from("Need a trace file or a covariance file for parameter draws."))?
+            }
+        };
+    println!("Classifying over {} parameter draws.", draws.len());
+    Ok(draws)
+}
+
+fn read_covariance_file(file: &str, n_traits: usize) -> Result<Matrix, Error> {
+    //  Whitespace-separated square matrix in the order mu, tau, betas, sigmas
+    let n_params = ParamIndex::n_params(n_traits);
+    let content = for_file(file, read_to_string(file))?;
+    let rows =
+        content.lines().filter(|line| !line.trim().is_empty()).map(|line| {
+            line.split_whitespace().map(|value| value.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+        }).collect::<Result<Vec<Vec<f64>>, _>>()?;
+    if rows.len() != n_params || rows.iter().any(|row| row.len() != n_params) {
+        Err(Error::from(format!("Covariance in {} needs to be {} by {}.", file, n_params,
+                                n_params)))?;
+    }
+    Ok(Matrix::fill(n_params, n_params, |i, j| rows[i][j]))
+}
+
+fn draw_gaussian(params: &Params, covariance: &Matrix, n_draws: usize, seed: u64)
+                 -> Result<Vec<Params>, Error> {
+    let n_traits = params.n_traits();
+    let lower =
+        covariance.cholesky()
+            .ok_or_else(|| Error::from("Covariance of params is not positive definite."))?;
+    let means: Vec<f64> =
+        ParamIndex::all(n_traits).map(|param_index| params[param_index]).collect();
+    let n_params = means.len();
+    let mut rng = new_rng(seed, RngStream::ParamDraws);
+    let mut draws: Vec<Params> = Vec::with_capacity(n_draws);
+    for _ in 0..n_draws {
+        let zs: Vec<f64> = (0..n_params).map(|_| StandardNormal.sample(&mut rng)).collect();
+        let values: Vec<f64> =
+            (0..n_params).map(|i| {
+                means[i] + (0..=i).map(|k| lower[i][k] * zs[k]).sum::<f64>()
+            }).collect();
+        let mut draw = Params::from_vec(&values, params.trait_names.clone())?;
+        //  The likelihood only depends on tau and sigmas through their squares
+        draw.tau = draw.tau.abs();
+        draw.sigmas.iter_mut().for_each(|sigma| *sigma = sigma.abs());
+        draws.push(draw)
+    }
+    Ok(draws)
+}
+
+pub(crate) fn calculate_param_uncertainty(draws: &[Params], data: &GwasData, is_col: &[usize],
+                                          e_options: &EOptions) -> ParamUncertainty {
+    let n_draws = draws.len() as f64;
+    let e_posteriors: Vec<EPosterior> =
+        draws.iter().map(|draw| {
+            let params = draw.reduce_to(data.meta.trait_names.clone(), is_col);
+            calculate_e_posterior(&params, &data.betas[0], &data.ses[0])
+        }).collect();
+    let e_mean = e_posteriors.iter().map(|e_posterior| e_posterior.mean).sum::<f64>() / n_draws;
+    let e_var_data =
+        e_posteriors.iter().map(|e_posterior| e_posterior.variance).sum::<f64>() / n_draws;
+    let e_var_params =
+        (e_posteriors.iter().map(|e_posterior| e_posterior.mean.powi(2)).sum::<f64>() / n_draws
+            - e_mean.powi(2)).max(0.0);
+    let e_std = (e_var_data + e_var_params).sqrt();
+    let e_quantiles: Vec<f64> =
+        e_options.quantile_probs.iter().map(|&p| mixture_quantile(&e_posteriors, p)).collect();
+    let p_pos = 1.0 - mixture_cdf(&e_posteriors, 0.0);
+    let p_neg = mixture_cdf(&e_posteriors, 0.0);
+    let p_above_threshold =
+        e_options.threshold.map(|threshold| {
+            1.0 - mixture_cdf(&e_posteriors, threshold) + mixture_cdf(&e_posteriors, -threshold)
+        });
+    let lfsr = (1.0 - p_pos).min(1.0 - p_neg);
+    ParamUncertainty {
+        e_mean, e_std, e_var_data, e_var_params, e_quantiles, p_pos, p_neg, p_above_threshold,
+        lfsr
+    }
+}
+
+fn mixture_cdf(e_posteriors: &[EPosterior], x: f64) -> f64 {
+    e_posteriors.iter().map(|e_posterior| {
+        normal_cdf((x - e_posterior.mean) / e_posterior.variance.sqrt())
+    }).sum::<f64>() / (e_posteriors.len() as f64)
+}
+
+fn mixture_quantile(e_posteriors: &[EPosterior], p: f64) -> f64 {
+    //  By bisection, between bounds well outside all components
+    let mut lower =
+        e_posteriors.iter().map(|e_posterior| {
+            e_posterior.mean - 10.0 * e_posterior.variance.sqrt()
+        }).fold(f64::INFINITY, f64::min);
+    let mut upper =
+        e_posteriors.iter().map(|e_posterior| {
+            e_posterior.mean + 10.0 * e_posterior.variance.sqrt()
+        }).fold(f64::NEG_INFINITY, f64::max);
+    for _ in 0..defaults::N_BISECTIONS {
+        let middle = 0.5 * (lower + upper);
+        if mixture_cdf(e_posteriors, middle) < p {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+    0.5 * (lower + upper)
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::fs::{File, read_to_string};
+use std::path::Path;
+use crate::error::{Error, for_file};
+
+//  Entries of a temp file from an earlier, interrupted run, by variant id.
+pub(crate) struct SavedEntries {
+    pub(crate) entries: HashMap<String, Vec<String>>,
+}
+
+impl SavedEntries {
+    pub(crate) fn new() -> SavedEntries {
+        let entries: HashMap<String, Vec<String>> = HashMap::new();
+        SavedEntries { entries }
+    }
+    pub(crate) fn contains(&self, id: &str) -> bool { self.entries.contains_key(id) }
+    pub(crate) fn n_entries(&self) -> usize { self.entries.len() }
+}
+
+//  Reads the temp file, keeping complete lines only, and truncates it after the last of them,
+//  so that new entries can be appended.
+pub(crate) fn read_temp_file(file: &str, header_cols: &[String]) -> Result<SavedEntries, Error> {
+    let mut saved_entries = SavedEntries::new();
+    if !Path::new(file).exists() {
+        println!("No temp file {} to resume from, so starting from scratch.", file);
+        return Ok(saved_entries)
+    }
+    let content = for_file(file, read_to_string(file))?;
+    let mut lines = content.split_inclusive('\n');
+    let header = lines.next().unwrap_or("");
+    if header.trim_end_matches('\n').split('\t').ne(header_cols.iter().map(|col| col.as_str())) {
+        Err(Error::from(format!("Header of temp file {} does not match the current config, \
+        so cannot resume.", file)))?;
+    }
+    let mut n_bytes_valid = header.len();
+    for line in lines {
+        let values: Vec<String> =
+            line.trim_end_matches('\n').split('\t').map(|value| value.to_string()).collect();
+        if !line.ends_with('\n') || values.len() != header_cols.len() {
+            println!("Dropping partially written last line of temp file.");
+            break
+        }
+        n_bytes_valid += line.len();
+        saved_entries.entries.insert(values[0].clone(), values);
+    }
+    let file_handle = for_file(file, File::options().write(true).open(file))?;
+    for_file(file, file_handle.set_len(n_bytes_valid as u64))?;
+    println!("Resuming with {} variants already classified.", saved_entries.n_entries());
+    Ok(saved_entries)
+}
+
+//  Seed of the entries in the temp file, if there are any
+pub(crate) fn read_saved_seed(file: &str) -> Result<Option<u64>, Error> {
+    if !Path::new(file).exists() {
+        return Ok(None)
+    }
+    let content = for_file(file, read_to_string(file))?;
+    let mut lines = content.lines();
+    let i_col_seed =
+        lines.next().and_then(|header| header.split('\t').position(|col| col == "seed"));
+    let seed =
+        match (i_col_seed, lines.next()) {
+            (Some(i_col_seed), Some(line)) => {
+                line.split('\t').nth(i_col_seed).and_then(|value| value.parse::<u64>().ok())
+            }
+            _ => { None }
+        };
+    Ok(seed)
+}
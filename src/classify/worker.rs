@@ -1,16 +1,22 @@
 use std::fs::File;
 use std::io::{BufWriter, Write};
-use std::sync::Arc;
 use std::sync::mpsc::{Receiver, Sender};
-use rand::prelude::ThreadRng;
-use rand::thread_rng;
-use crate::classify::{Classification, MessageToCentral, MessageToWorker};
+use crate::classify::{Classification, ClassifyWorkerLauncher, defaults, e_options,
+                      MessageToCentral, MessageToWorker};
+use crate::math::batch_means::BatchMeans;
 use crate::data::GwasData;
-use crate::options::config::ClassifyConfig;
+use crate::likelihood::log10_bayes_factor;
+use crate::options::config::{AdaptiveConfig, ClassifyConfig, ClassifyMethod, KernelType};
 use crate::sample::vars::Vars;
 use crate::params::Params;
 use crate::sample::sampler::{ETracer, Sampler};
-use crate::classify::exact::calculate_mu;
+use crate::sample::var_stats::{EOptions, SampledClassification};
+use crate::classify::models::{best_model, classify_models};
+use crate::classify::param_draws::calculate_param_uncertainty;
+use crate::util::rng::{new_rng, RngStream, SeededRng};
+use crate::classify::exact::{calculate_contributions, calculate_exact_classification,
+                             calculate_heterogeneity, calculate_shrinkages, predict_t};
+use crate::sample::conditionals::calculate_e_posterior;
 
 struct ClassifyETracer<W: Write> {
     writer: W,
@@ -24,52 +30,179 @@ impl<W: Write> ETracer for ClassifyETracer<W> {
     }
 }
 
-pub(crate) fn classify_worker(data: &Arc<GwasData>, params: &Params, config: ClassifyConfig,
+fn expand_to_all_traits(values: &[f64], is_col: &[usize], n_traits: usize) -> Vec<f64> {
+    let mut values_all: Vec<f64> = vec![f64::NAN; n_traits];
+    for (&value, &i_col) in values.iter().zip(is_col.iter()) {
+        values_all[i_col] = value;
+    }
+    values_all
+}
+
+struct SamplingSummary {
+    n_samples: usize,
+    e_mcse: f64,
+    e_autocorrelation: f64,
+}
+
+//  MCSE is measured against the posterior SD of E, not the spread of the batch-means values,
+//  which with Rao-Blackwellization is the smaller spread of the conditional means.
+fn is_precise_enough(adaptive: &AdaptiveConfig, e_batch_means: &BatchMeans, e_std: f64)
+                     -> bool {
+    let n_samples = e_batch_means.n();
+    let mcse_fraction = adaptive.mcse_fraction.unwrap_or(defaults::ADAPTIVE_MCSE_FRACTION);
+    n_samples >= adaptive.n_samples_max
+        || (n_samples >= adaptive.n_samples_min
+        && e_batch_means.mcse() <= mcse_fraction * e_std)
+}
+
+fn sample_classification(data: &GwasData, params: &Params, config: &ClassifyConfig,
+                         e_options: &EOptions, rng: SeededRng)
+                         -> (SampledClassification, SamplingSummary) {
+    let mut vars = Vars::initial_vars(data, params);
+    let meta = data.meta.clone();
+    let kernel_type = config.kernel.unwrap_or(KernelType::Gibbs);
+    let rao_blackwell = config.rao_blackwell.unwrap_or(false);
+    let mut sampler =
+        Sampler::<SeededRng>::with_e_options(&meta, rng, kernel_type, rao_blackwell, e_options);
+    let mut e_tracer =
+        match (&config.trace_ids, data.meta.var_ids.first()) {
+            (Some(trace_ids), Some(var_id))
+            if trace_ids.contains(var_id)
+            => {
+                let trace_file_name = {
+                    let mut temp = config.out_file.clone();
+                    temp.push('_');
+                    temp.push_str(var_id);
+                    temp
+                };
+                match File::create(trace_file_name) {
+                    Ok(file) => {
+                        let writer = BufWriter::new(file);
+                        let e_tracer = ClassifyETracer { writer };
+                        Some(Box::new(e_tracer) as Box<dyn ETracer>)
+                    }
+                    Err(error) => {
+                        println!("Could not create E trace file: {}", error);
+                        None
+                    }
+                }
+            }
+            _ => { None }
+        };
+    sampler.sample_n(data, params, &mut vars, config.n_steps_burn_in, &mut e_tracer);
+    let mut e_batch_means = BatchMeans::new();
+    loop {
+        let n_steps =
+            match &config.adaptive {
+                None => { config.n_samples }
+                Some(adaptive) => {
+                    adaptive.block_size.unwrap_or(defaults::ADAPTIVE_BLOCK_SIZE)
+                }
+            };
+        for _ in 0..n_steps {
+            sampler.sample_one(data, params, &mut vars, &mut e_tracer);
+            e_batch_means.add(sampler.var_stats().e_estimate(params, &vars, 0));
+        }
+        match &config.adaptive {
+            None => { break }
+            Some(adaptive) => {
+                let e_std = sampler.var_stats().calculate_classification().e_std;
+                if is_precise_enough(adaptive, &e_batch_means, e_std) {
+                    break
+                }
+            }
+        }
+    }
+    let n_samples = e_batch_means.n();
+    let e_mcse = e_batch_means.mcse();
+    let e_autocorrelation = e_batch_means.autocorrelation_lag1();
+    let sampling_summary = SamplingSummary { n_samples, e_mcse, e_autocorrelation };
+    (sampler.var_stats().calculate_classification(), sampling_summary)
+}
+
+pub(crate) fn classify_worker(launcher: ClassifyWorkerLauncher,
                               sender: Sender<MessageToCentral>,
                               receiver: Receiver<MessageToWorker>, i_thread: usize) {
+    let ClassifyWorkerLauncher { data, params, models, param_draws, seed, config } = launcher;
+    let e_options = e_options(&config);
     loop {
         let in_message = receiver.recv().unwrap();
         match in_message {
             MessageToWorker::DataPoint(i_data_point) => {
+                let n_traits = data.n_traits();
                 let (data, is_col) = data.only_data_point(i_data_point);
                 let trait_names = data.meta.trait_names.clone();
+                let params_all = &params;
                 let params = params.reduce_to(trait_names, &is_col);
-                let mut vars = Vars::initial_vars(&data, &params);
-                let rng = thread_rng();
-                let meta = data.meta.clone();
-                let mut sampler = Sampler::<ThreadRng>::new(&meta, rng);
-                let mut e_tracer =
-                    match (&config.trace_ids, data.meta.var_ids.first()) {
-                        (Some(trace_ids), Some(var_id))
-                        if trace_ids.contains(var_id)
-                        => {
-                            let trace_file_name = {
-                                let mut temp = config.out_file.clone();
-                                temp.push('_');
-                                temp.push_str(var_id);
-                                temp
-                            };
-                            match File::create(trace_file_name) {
-                                Ok(file) => {
-                                    let writer = BufWriter::new(file);
-                                    let e_tracer = ClassifyETracer { writer };
-                                    Some(Box::new(e_tracer) as Box<dyn ETracer>)
-                                }
-                                Err(error) => {
-                                    println!("Could not create E trace file: {}", error);
-                                    None
-                                }
-                            }
+                let (mut sampled, sampling_summary) =
+                    match config.method.unwrap_or(ClassifyMethod::Sample) {
+                        ClassifyMethod::Sample => {
+                            let rng = new_rng(seed, RngStream::ClassifyVariant(i_data_point));
+                            sample_classification(&data, &params, &config, &e_options, rng)
                         }
-                        _ => { None }
+                        ClassifyMethod::Exact => {
+                            let sampled =
+                                calculate_exact_classification(&params, &data.betas[0],
+                                                               &data.ses[0], &e_options);
+                            let sampling_summary =
+                                SamplingSummary {
+                                    n_samples: 0, e_mcse: f64::NAN, e_autocorrelation: f64::NAN
+                                };
+                            (sampled, sampling_summary)
+                        }
+                    };
+                let SamplingSummary { n_samples, e_mcse, e_autocorrelation } = sampling_summary;
+                sampled.t_means = expand_to_all_traits(&sampled.t_means, &is_col, n_traits);
+                sampled.t_stds = expand_to_all_traits(&sampled.t_stds, &is_col, n_traits);
+                let shrinkages =
+                    expand_to_all_traits(&calculate_shrinkages(&params, &data.ses[0]), &is_col,
+                                         n_traits);
+                let betas_observed = expand_to_all_traits(&data.betas[0], &is_col, n_traits);
+                let ses_observed = expand_to_all_traits(&data.ses[0], &is_col, n_traits);
+                let e_posterior =
+                    calculate_e_posterior(&params, &data.betas[0], &data.ses[0]);
+                let mut contributions =
+                    calculate_contributions(&params, &data.betas[0], &data.ses[0]);
+                contributions.weight_fracs =
+                    expand_to_all_traits(&contributions.weight_fracs, &is_col, n_traits);
+                contributions.contrib_fracs =
+                    expand_to_all_traits(&contributions.contrib_fracs, &is_col, n_traits);
+                let mut imputed: Vec<bool> = vec![true; n_traits];
+                for &i_col in &is_col {
+                    imputed[i_col] = false;
+                }
+                for i_trait in (0..n_traits).filter(|&i_trait| imputed[i_trait]) {
+                    let prediction =
+                        predict_t(params_all.betas[i_trait], params_all.sigmas[i_trait],
+                                  &e_posterior);
+                    sampled.t_means[i_trait] = prediction.mean;
+                    sampled.t_stds[i_trait] = prediction.variance.sqrt();
+                }
+                let mut heterogeneity =
+                    calculate_heterogeneity(&params, &data.betas[0], &data.ses[0]);
+                heterogeneity.residuals = expand_to_all_traits(&heterogeneity.residuals, &is_col,
+                                                               n_traits);
+                let e_mean_calculated = e_posterior.mean;
+                let e_std_calculated = e_posterior.variance.sqrt();
+                let log10_bayes_factor =
+                    log10_bayes_factor(&params, &data.betas[0], &data.ses[0]);
+                let model_classifications = classify_models(&models, &data, &is_col);
+                let best_model =
+                    best_model(&models, &model_classifications).map(|name| name.to_string());
+                let param_uncertainty =
+                    if param_draws.is_empty() {
+                        None
+                    } else {
+                        Some(calculate_param_uncertainty(&param_draws, &data, &is_col,
+                                                         &e_options))
+                    };
+                let classification =
+                    Classification {
+                        sampled, n_samples, seed, e_mcse, e_autocorrelation, e_mean_calculated,
+                        e_std_calculated, log10_bayes_factor, heterogeneity, shrinkages,
+                        betas_observed, ses_observed, imputed, contributions,
+                        models: model_classifications, best_model, param_uncertainty
                     };
-                sampler.sample_n(&data, &params, &mut vars, config.n_steps_burn_in, &mut
-                    e_tracer);
-                sampler.sample_n(&data, &params, &mut vars, config.n_samples, &mut e_tracer);
-                let sampled = sampler.var_stats().calculate_classification();
-                let mu_calculated =
-                    calculate_mu(&params, &data.betas[0], &data.ses[0]);
-                let classification = Classification { sampled, e_mean_calculated: mu_calculated };
                 sender.send(MessageToCentral { i_thread, classification }).unwrap();
             }
             MessageToWorker::Shutdown => {
@@ -77,4 +210,59 @@ pub(crate) fn classify_worker(data: &Arc<GwasData>, params: &Params, config: Cla
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::classify::worker::sample_classification;
+    use crate::data::{GwasData, Meta};
+    use crate::math::matrix::Matrix;
+    use crate::options::config::{AdaptiveConfig, ClassifyConfig, KernelType};
+    use crate::params::Params;
+    use crate::sample::var_stats::EOptions;
+    use crate::util::rng::{new_rng, RngStream};
+
+    fn n_samples_until_precise(sigma: f64, rao_blackwell: bool) -> f64 {
+        let trait_names = Arc::new(vec!["a".to_string(), "b".to_string()]);
+        let var_ids = Arc::new(vec!["v".to_string()]);
+        let meta = Meta { trait_names: trait_names.clone(), var_ids };
+        let betas = Matrix::fill(1, 2, |_, i_trait| [0.8, 1.3][i_trait]);
+        let ses = Matrix::fill(1, 2, |_, _| 0.5);
+        let data = GwasData { meta, betas, ses };
+        let params =
+            Params {
+                trait_names, mu: 0.0, tau: 1.0, betas: vec![1.0, 1.0], sigmas: vec![sigma, sigma]
+            };
+        let adaptive =
+            AdaptiveConfig {
+                n_samples_min: 100, n_samples_max: 1000000, block_size: Some(100),
+                mcse_fraction: Some(0.02)
+            };
+        let config =
+            ClassifyConfig {
+                params_override: None, n_steps_burn_in: 1000, n_samples: 0,
+                out_file: String::new(), trace_ids: None, e_quantiles: None, e_threshold: None,
+                method: None, models: None, param_draws: None, adaptive: Some(adaptive),
+                seed: None, kernel: Some(KernelType::Gibbs), rao_blackwell: Some(rao_blackwell),
+            };
+        let rng = new_rng(7, RngStream::ClassifyVariant(0));
+        let (_, sampling_summary) =
+            sample_classification(&data, &params, &config, &EOptions::default(), rng);
+        sampling_summary.n_samples as f64
+    }
+
+    #[test]
+    fn rao_blackwell_stops_like_plain_sampling() {
+        //  With small sigmas, E given the Ts is nearly fixed and conditioning gains little, so
+        //  both stop after a similar number of samples.
+        let n_samples_plain = n_samples_until_precise(0.2, false);
+        let n_samples_rao_blackwell = n_samples_until_precise(0.2, true);
+        assert!(n_samples_rao_blackwell <= 1.5 * n_samples_plain);
+        assert!(n_samples_rao_blackwell >= 0.5 * n_samples_plain);
+        //  With large sigmas, the conditional means spread much less than E, which must make
+        //  Rao-Blackwellized sampling stop sooner, not later.
+        let n_samples_plain = n_samples_until_precise(1.0, false);
+        let n_samples_rao_blackwell = n_samples_until_precise(1.0, true);
+        assert!(n_samples_rao_blackwell <= 0.5 * n_samples_plain);
+    }
+}
@@ -0,0 +1,28 @@
+pub(crate) mod traits;
+pub(crate) mod folds;
+
+use crate::data::Weights;
+
+mod defaults {
+    pub(crate) const N_ITERATIONS: usize = 1000;
+}
+
+pub(crate) fn weighted_correlation(xs: &[f64], ys: &[f64], weights: &Weights) -> f64 {
+    let mean_x: f64 =
+        xs.iter().zip(weights.weights.iter()).map(|(x, w)| w * x).sum::<f64>() / weights.sum;
+    let mean_y: f64 =
+        ys.iter().zip(weights.weights.iter()).map(|(y, w)| w * y).sum::<f64>() / weights.sum;
+    let mut cov: f64 = 0.0;
+    let mut var_x: f64 = 0.0;
+    let mut var_y: f64 = 0.0;
+    for ((x, y), w) in xs.iter().zip(ys.iter()).zip(weights.weights.iter()) {
+        cov += w * (x - mean_x) * (y - mean_y);
+        var_x += w * (x - mean_x).powi(2);
+        var_y += w * (y - mean_y).powi(2);
+    }
+    cov / (var_x * var_y).sqrt()
+}
+
+pub(crate) fn normal_log_density(x: f64, mean: f64, variance: f64) -> f64 {
+    -0.5 * ((2.0 * std::f64::consts::PI * variance).ln() + (x - mean).powi(2) / variance)
+}
@@ -0,0 +1,142 @@
+use rand::seq::SliceRandom;
+use crate::check::check_config;
+use crate::sample::conditionals::calculate_e_posterior;
+use crate::cv::{defaults, normal_log_density};
+use crate::data::{GwasData, load_data, Weights};
+use crate::error::Error;
+use crate::likelihood::variant_log_likelihood;
+use crate::options::action::Action;
+use crate::options::cli::CvFoldsOptions;
+use crate::options::config::load_config;
+use crate::params::Params;
+use crate::train::em::fit_exact;
+use crate::train::initial_params::estimate_initial_params;
+use crate::train::param_meta_stats::str18;
+use crate::util::rng::{new_rng, RngStream, seed_or_random};
+
+struct TraitErrors {
+    weight_sum: f64,
+    squared_error_sum: f64,
+    z_squared_sum: f64,
+    log_pred_dens_sum: f64,
+}
+
+impl TraitErrors {
+    fn new() -> TraitErrors {
+        let weight_sum: f64 = 0.0;
+        let squared_error_sum: f64 = 0.0;
+        let z_squared_sum: f64 = 0.0;
+        let log_pred_dens_sum: f64 = 0.0;
+        TraitErrors { weight_sum, squared_error_sum, z_squared_sum, log_pred_dens_sum }
+    }
+    fn add(&mut self, weight: f64, observed: f64, predicted: f64, variance: f64) {
+        self.weight_sum += weight;
+        self.squared_error_sum += weight * (observed - predicted).powi(2);
+        self.z_squared_sum += weight * (observed - predicted).powi(2) / variance;
+        self.log_pred_dens_sum += weight * normal_log_density(observed, predicted, variance);
+    }
+    fn plus(&mut self, other: &TraitErrors) {
+        self.weight_sum += other.weight_sum;
+        self.squared_error_sum += other.squared_error_sum;
+        self.z_squared_sum += other.z_squared_sum;
+        self.log_pred_dens_sum += other.log_pred_dens_sum;
+    }
+    fn rmse(&self) -> f64 { (self.squared_error_sum / self.weight_sum).sqrt() }
+    fn mean_z_squared(&self) -> f64 { self.z_squared_sum / self.weight_sum }
+    fn mean_log_pred_dens(&self) -> f64 { self.log_pred_dens_sum / self.weight_sum }
+}
+
+fn mean_log_likelihood(data: &GwasData, weights: &Weights, params: &Params) -> f64 {
+    (0..data.n_data_points()).map(|j| {
+        weights.weights[j] * variant_log_likelihood(params, &data.betas[j], &data.ses[j])
+    }).sum::<f64>() / weights.sum
+}
+
+fn masked_trait_errors(data: &GwasData, weights: &Weights, params: &Params) -> Vec<TraitErrors> {
+    let n_traits = data.n_traits();
+    (0..n_traits).map(|i_masked| {
+        let is_col: Vec<usize> = (0..n_traits).filter(|&i| i != i_masked).collect();
+        let data_masked = data.only_traits(&is_col);
+        let params_masked = params.reduce_to(data_masked.meta.trait_names.clone(), &is_col);
+        let beta = params.betas[i_masked];
+        let sigma = params.sigmas[i_masked];
+        let mut errors = TraitErrors::new();
+        for j in 0..data.n_data_points() {
+            let e_posterior =
+                calculate_e_posterior(&params_masked, &data_masked.betas[j],
+                                      &data_masked.ses[j]);
+            let predicted = beta * e_posterior.mean;
+            let variance = beta.powi(2) * e_posterior.variance + sigma.powi(2)
+                + data.ses[j][i_masked].powi(2);
+            errors.add(weights.weights[j], data.betas[j][i_masked], predicted, variance);
+        }
+        errors
+    }).collect()
+}
+
+pub(crate) fn cross_validate_folds(options: &CvFoldsOptions) -> Result<(), Error> {
+    let config = load_config(&options.config_file)?;
+    check_config(&config)?;
+    let data = load_data(&config, Action::Train)?;
+    let gwas_data = &data.gwas_data;
+    let n_data_points = gwas_data.n_data_points();
+    let n_traits = gwas_data.n_traits();
+    let n_folds = options.n_folds;
+    if n_folds < 2 || n_folds > n_data_points {
+        Err(Error::from(format!("Number of folds needs to be between 2 and the number of \
+        variants ({}), but is {}.", n_data_points, n_folds)))?;
+    }
+    println!("Loaded data for {} variants, splitting into {} folds.", n_data_points, n_folds);
+    //  Variants are in id order, i.e. mostly by position, so folds are assigned after shuffling
+    let seed = seed_or_random(config.train.seed);
+    println!("Random seed is {}", seed);
+    let mut i_folds: Vec<usize> = (0..n_data_points).map(|j| j % n_folds).collect();
+    i_folds.shuffle(&mut new_rng(seed, RngStream::CvFolds));
+    let trait_names = gwas_data.meta.trait_names.clone();
+    let mut train_log_lik_sum: f64 = 0.0;
+    let mut test_log_lik_sum: f64 = 0.0;
+    let mut test_weight_sum: f64 = 0.0;
+    let mut errors_total: Vec<TraitErrors> = (0..n_traits).map(|_| TraitErrors::new()).collect();
+    for i_fold in 0..n_folds {
+        let (is_test, is_train): (Vec<usize>, Vec<usize>) =
+            (0..n_data_points).partition(|&j| i_folds[j] == i_fold);
+        let data_train = gwas_data.only_data_points(&is_train);
+        let data_test = gwas_data.only_data_points(&is_test);
+        let weights_train = data.weights.only_data_points(&is_train);
+        let weights_test = data.weights.only_data_points(&is_test);
+        let params_initial = estimate_initial_params(&data_train)?;
+        let params =
+            fit_exact(&data_train, &weights_train, &params_initial, defaults::N_ITERATIONS);
+        let train_log_lik = mean_log_likelihood(&data_train, &weights_train, &params);
+        let test_log_lik = mean_log_likelihood(&data_test, &weights_test, &params);
+        println!("Fold {}: {} training and {} held-out variants", i_fold, is_train.len(),
+                 is_test.len());
+        println!("Log-likelihood per variant: training {}, held-out {}", train_log_lik,
+                 test_log_lik);
+        let errors = masked_trait_errors(&data_test, &weights_test, &params);
+        print_trait_errors(&trait_names, &errors);
+        train_log_lik_sum += weights_train.sum * train_log_lik;
+        test_log_lik_sum += weights_test.sum * test_log_lik;
+        test_weight_sum += weights_test.sum;
+        for (error_total, error) in errors_total.iter_mut().zip(errors.iter()) {
+            error_total.plus(error);
+        }
+    }
+    let train_weight_sum = ((n_folds - 1) as f64) * data.weights.sum;
+    println!("All folds:");
+    println!("Log-likelihood per variant: training {}, held-out {}",
+             train_log_lik_sum / train_weight_sum, test_log_lik_sum / test_weight_sum);
+    print_trait_errors(&trait_names, &errors_total);
+    println!("Trait errors are for predicting a held-out variant's masked trait from its other \
+    traits; mean z^2 near 1 means well calibrated.");
+    Ok(())
+}
+
+fn print_trait_errors(trait_names: &[String], errors: &[TraitErrors]) {
+    println!("{} {} {} {}", str18("masked_trait"), str18("rmse"), str18("mean_z2"),
+             str18("log_pred_dens"));
+    for (trait_name, error) in trait_names.iter().zip(errors.iter()) {
+        println!("{} {} {} {}", str18(trait_name), str18(error.rmse()),
+                 str18(error.mean_z_squared()), str18(error.mean_log_pred_dens()));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::check::check_config;
+use crate::sample::conditionals::{calculate_e_posterior, EPosterior};
+use crate::cv::{defaults, normal_log_density, weighted_correlation};
+use crate::data::load_data;
+use crate::error::Error;
+use crate::likelihood::compare::fit_unloaded_sigma;
+use crate::options::action::Action;
+use crate::options::cli::CvTraitsOptions;
+use crate::options::config::load_config;
+use crate::train::em::fit_exact;
+use crate::train::initial_params::estimate_initial_params;
+use crate::train::param_meta_stats::str18;
+
+//  Weighted sums for regressing a held-out trait on the posterior E, from which the loading and
+//  residual variance follow in closed form, also with any one variant left out.
+struct LoadingSums {
+    weight: f64,
+    o2: f64,
+    o_e: f64,
+    e2: f64,
+    se2: f64,
+}
+
+struct Loading {
+    beta: f64,
+    var_residual: f64,
+}
+
+impl LoadingSums {
+    fn new() -> LoadingSums {
+        LoadingSums { weight: 0.0, o2: 0.0, o_e: 0.0, e2: 0.0, se2: 0.0 }
+    }
+    fn variant(weight: f64, observed: f64, se: f64, e_posterior: &EPosterior) -> LoadingSums {
+        LoadingSums {
+            weight,
+            o2: weight * observed.powi(2),
+            o_e: weight * observed * e_posterior.mean,
+            e2: weight * (e_posterior.variance + e_posterior.mean.powi(2)),
+            se2: weight * se.powi(2),
+        }
+    }
+    fn add(&mut self, other: &LoadingSums) {
+        self.weight += other.weight;
+        self.o2 += other.o2;
+        self.o_e += other.o_e;
+        self.e2 += other.e2;
+        self.se2 += other.se2;
+    }
+    fn minus(&self, other: &LoadingSums) -> LoadingSums {
+        LoadingSums {
+            weight: self.weight - other.weight,
+            o2: self.o2 - other.o2,
+            o_e: self.o_e - other.o_e,
+            e2: self.e2 - other.e2,
+            se2: self.se2 - other.se2,
+        }
+    }
+    fn loading(&self) -> Loading {
+        let beta = self.o_e / self.e2;
+        let var_residual =
+            ((self.o2 - 2.0 * beta * self.o_e + beta.powi(2) * self.e2 - self.se2)
+                / self.weight).max(0.0);
+        Loading { beta, var_residual }
+    }
+}
+
+pub(crate) fn cross_validate_traits(options: &CvTraitsOptions) -> Result<(), Error> {
+    let config = load_config(&options.config_file)?;
+    check_config(&config)?;
+    let data = load_data(&config, Action::Train)?;
+    let gwas_data = &data.gwas_data;
+    let weights = &data.weights;
+    let n_traits = gwas_data.n_traits();
+    let n_data_points = gwas_data.n_data_points();
+    if n_traits < 2 {
+        Err(Error::from("Need at least two traits to leave one out."))?;
+    }
+    println!("Loaded data for {} variants", n_data_points);
+    let params_initial = estimate_initial_params(gwas_data)?;
+    let params_full =
+        fit_exact(gwas_data, weights, &params_initial, defaults::N_ITERATIONS)
+            .normalized_with_mu_one();
+    println!("Fitted all traits:\n{}", params_full);
+    println!("{} {} {} {} {} {}", str18("trait"), str18("beta_full"), str18("beta_held_out"),
+             str18("correlation"), str18("log_pred_dens"), str18("log_pred_dens_null"));
+    for i_held_out in 0..n_traits {
+        let is_col: Vec<usize> = (0..n_traits).filter(|&i| i != i_held_out).collect();
+        let data_reduced = gwas_data.only_traits(&is_col);
+        let params_start =
+            params_full.reduce_to(data_reduced.meta.trait_names.clone(), &is_col);
+        let params_reduced =
+            fit_exact(&data_reduced, weights, &params_start, defaults::N_ITERATIONS)
+                .normalized_with_mu_one();
+        let e_posteriors: Vec<_> = (0..n_data_points).map(|j| {
+            calculate_e_posterior(&params_reduced, &data_reduced.betas[j], &data_reduced.ses[j])
+        }).collect();
+        let observed: Vec<f64> =
+            (0..n_data_points).map(|j| gwas_data.betas[j][i_held_out]).collect();
+        let ses: Vec<f64> = (0..n_data_points).map(|j| gwas_data.ses[j][i_held_out]).collect();
+        //  Loading and residual variance of the held-out trait on the posterior E, by regression.
+        //  Each variant is predicted from a loading fitted without it.
+        let variant_sums: Vec<LoadingSums> =
+            (0..n_data_points).map(|j| {
+                LoadingSums::variant(weights.weights[j], observed[j], ses[j], &e_posteriors[j])
+            }).collect();
+        let mut sums = LoadingSums::new();
+        variant_sums.iter().for_each(|variant_sum| sums.add(variant_sum));
+        let beta = sums.loading().beta;
+        let loadings_loo: Vec<Loading> =
+            variant_sums.iter().map(|variant_sum| sums.minus(variant_sum).loading()).collect();
+        let predicted: Vec<f64> =
+            e_posteriors.iter().zip(loadings_loo.iter())
+                .map(|(e_posterior, loading)| loading.beta * e_posterior.mean).collect();
+        let correlation = weighted_correlation(&observed, &predicted, weights);
+        let log_pred_dens =
+            (0..n_data_points).map(|j| {
+                let Loading { beta, var_residual } = loadings_loo[j];
+                let variance =
+                    beta.powi(2) * e_posteriors[j].variance + var_residual + ses[j].powi(2);
+                weights.weights[j] * normal_log_density(observed[j], predicted[j], variance)
+            }).sum::<f64>() / weights.sum;
+        let sigma_null = fit_unloaded_sigma(gwas_data, weights, i_held_out);
+        let log_pred_dens_null =
+            (0..n_data_points).map(|j| {
+                let variance = sigma_null.powi(2) + ses[j].powi(2);
+                weights.weights[j] * normal_log_density(observed[j], 0.0, variance)
+            }).sum::<f64>() / weights.sum;
+        println!("{} {} {} {} {} {}", str18(&gwas_data.meta.trait_names[i_held_out]),
+                 str18(params_full.betas[i_held_out]), str18(beta), str18(correlation),
+                 str18(log_pred_dens), str18(log_pred_dens_null));
+    }
+    println!("Correlations and log predictive densities use loadings fitted with the predicted \
+    variant left out. Log predictive densities are means per variant; the null has the trait not \
+    loading on E.");
+    Ok(())
+}
@@ -17,7 +17,7 @@ const DELIM_LIST: &[char; 4] = &[';', '\t', ',', ' '];
 const MISSING_DELIM_MSG: &str =
     "Can only parse data files with semicolon, tab, comma or single blank as delimiter.";
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub(crate) struct Meta {
     pub(crate) trait_names: Arc<Vec<String>>,
     pub(crate) var_ids: Arc<Vec<String>>,
@@ -63,6 +63,33 @@ impl Meta {
 impl GwasData {
     pub(crate) fn n_data_points(&self) -> usize { self.meta.n_data_points() }
     pub(crate) fn n_traits(&self) -> usize { self.meta.n_traits() }
+    pub(crate) fn only_traits(&self, is_col: &[usize]) -> GwasData {
+        let trait_names: Arc<Vec<String>> =
+            Arc::new(is_col.iter().map(|&i_col|
+                self.meta.trait_names[i_col].clone())
+                .collect());
+        let var_ids = self.meta.var_ids.clone();
+        let meta = Meta { var_ids, trait_names };
+        let n_rows = meta.n_data_points();
+        let n_cols = meta.n_traits();
+        let betas =
+            Matrix::fill(n_rows, n_cols, |i_row, i_i_col| self.betas[i_row][is_col[i_i_col]]);
+        let ses =
+            Matrix::fill(n_rows, n_cols, |i_row, i_i_col| self.ses[i_row][is_col[i_i_col]]);
+        GwasData { meta, betas, ses }
+    }
+    pub(crate) fn only_data_points(&self, is_row: &[usize]) -> GwasData {
+        let var_ids: Arc<Vec<String>> =
+            Arc::new(is_row.iter().map(|&i_row| self.meta.var_ids[i_row].clone()).collect());
+        let trait_names = self.meta.trait_names.clone();
+        let meta = Meta { var_ids, trait_names };
+        let n_cols = meta.n_traits();
+        let betas =
+            Matrix::fill(is_row.len(), n_cols, |i_i_row, i_col| self.betas[is_row[i_i_row]][i_col]);
+        let ses =
+            Matrix::fill(is_row.len(), n_cols, |i_i_row, i_col| self.ses[is_row[i_i_row]][i_col]);
+        GwasData { meta, betas, ses }
+    }
     pub(crate) fn only_data_point(&self, i_row: usize) -> (GwasData, Vec<usize>) {
         let var_id = self.meta.var_ids[i_row].clone();
         let var_ids = Arc::new(vec![var_id]);
@@ -92,6 +119,13 @@ impl Weights {
         let sum: f64 = 0.0;
         Weights { weights, sum }
     }
+    pub(crate) fn from_vec(weights: Vec<f64>) -> Weights {
+        let sum: f64 = weights.iter().sum();
+        Weights { weights, sum }
+    }
+    pub(crate) fn only_data_points(&self, is_row: &[usize]) -> Weights {
+        Weights::from_vec(is_row.iter().map(|&i_row| self.weights[i_row]).collect())
+    }
     fn add(&mut self, weight: f64) {
         self.weights.push(weight);
         self.sum += weight;
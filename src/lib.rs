@@ -1,6 +1,6 @@
 use crate::check::check_config;
 use crate::error::Error;
-use crate::options::cli::{Choice, get_choice};
+use crate::options::cli::{Choice, get_choice, Resume};
 use crate::options::config::load_config;
 use crate::options::action::Action;
 use crate::options::check_pre::check_prerequisites;
@@ -17,19 +17,43 @@ mod phenet;
 mod params;
 mod sample;
 mod check;
+mod likelihood;
+mod cv;
 
 pub fn run() -> Result<(), Error> {
     match get_choice()? {
         Choice::Core(core_options) => {
-            let config = load_config(&core_options.config_file)?;
+            let mut config = load_config(&core_options.config_file)?;
+            if let Some(seed) = core_options.seed {
+                config.train.seed = Some(seed);
+                config.classify.seed = Some(seed);
+            }
             check_config(&config)?;
             check_prerequisites(&config)?;
             match core_options.action {
-                Action::Train => { train::train_or_check(&config, core_options.dry) }
-                Action::Classify => { classify::classify_or_check(&config, core_options.dry) }
+                Action::Train => {
+                    let checkpoint_file =
+                        match &core_options.resume {
+                            Some(Resume::Checkpoint(checkpoint_file)) => {
+                                Some(checkpoint_file.as_str())
+                            }
+                            _ => { None }
+                        };
+                    train::train_or_check(&config, core_options.dry, checkpoint_file)
+                }
+                Action::Classify => {
+                    let resume = matches!(core_options.resume, Some(Resume::TempFile));
+                    classify::classify_or_check(&config, core_options.dry, resume,
+                                                core_options.shard)
+                }
             }
         }
         Choice::ImportPhenet(options) => { phenet::import_phenet(&options) }
         Choice::ScaleSigmas(options) => { params::transform::scale_sigmas(&options)}
+        Choice::LogLik(options) => { likelihood::print_log_likelihood(&options) }
+        Choice::Compare(options) => { likelihood::compare::compare_models(&options) }
+        Choice::CvTraits(options) => { cv::traits::cross_validate_traits(&options) }
+        Choice::CvFolds(options) => { cv::folds::cross_validate_folds(&options) }
+        Choice::MergeClassify(options) => { classify::merge::merge_classify(&options) }
     }
 }
@@ -0,0 +1,86 @@
+pub(crate) mod compare;
+
+use std::f64::consts::PI;
+use crate::check::{check_config, check_params};
+use crate::data::{GwasData, load_data, Weights};
+use crate::error::Error;
+use crate::options::action::Action;
+use crate::options::cli::LogLikOptions;
+use crate::options::config::load_config;
+use crate::params::{Params, read_params_from_file};
+
+pub(crate) fn variant_log_likelihood(params: &Params, betas: &[f64], ses: &[f64]) -> f64 {
+    //  Observations are multivariate normal with mean beta*mu and covariance
+    //  tau^2*beta*beta^T + D, D = diag(sigma^2 + se^2), which has closed forms for the
+    //  determinant and inverse (matrix determinant lemma and Sherman-Morrison).
+    let tau2 = params.tau.powi(2);
+    let mut n_observed: usize = 0;
+    let mut log_det_diag: f64 = 0.0;
+    let mut sum_beta_beta: f64 = 0.0;
+    let mut sum_beta_res: f64 = 0.0;
+    let mut sum_res_res: f64 = 0.0;
+    for (((&beta, &sigma), &o), &se)
+    in params.betas.iter().zip(params.sigmas.iter()).zip(betas.iter()).zip(ses.iter()) {
+        if o.is_finite() && se.is_finite() {
+            n_observed += 1;
+            let d = sigma.powi(2) + se.powi(2);
+            let res = o - beta * params.mu;
+            log_det_diag += d.ln();
+            sum_beta_beta += beta.powi(2) / d;
+            sum_beta_res += beta * res / d;
+            sum_res_res += res.powi(2) / d;
+        }
+    }
+    let factor = 1.0 + tau2 * sum_beta_beta;
+    let log_det = log_det_diag + factor.ln();
+    let quad = sum_res_res - tau2 * sum_beta_res.powi(2) / factor;
+    -0.5 * ((n_observed as f64) * (2.0 * PI).ln() + log_det + quad)
+}
+
+pub(crate) fn log10_bayes_factor(params: &Params, betas: &[f64], ses: &[f64]) -> f64 {
+    //  Null model has E fixed at zero, so traits are explained by residual noise alone.
+    let params_null = Params { mu: 0.0, tau: 0.0, ..params.clone() };
+    let log_lik = variant_log_likelihood(params, betas, ses);
+    let log_lik_null = variant_log_likelihood(&params_null, betas, ses);
+    (log_lik - log_lik_null) / std::f64::consts::LN_10
+}
+
+pub(crate) fn log_likelihood(data: &GwasData, weights: &Weights, params: &Params) -> f64 {
+    (0..data.n_data_points()).map(|i_data_point| {
+        weights.weights[i_data_point]
+            * variant_log_likelihood(params, &data.betas[i_data_point], &data.ses[i_data_point])
+    }).sum()
+}
+
+pub(crate) fn print_log_likelihood(options: &LogLikOptions) -> Result<(), Error> {
+    let config = load_config(&options.config_file)?;
+    check_config(&config)?;
+    let params = read_params_from_file(&options.params_file)?;
+    check_params(&config, &params)?;
+    let data = load_data(&config, Action::Train)?;
+    println!("Loaded data for {} variants", data.gwas_data.meta.n_data_points());
+    let log_likelihood = log_likelihood(&data.gwas_data, &data.weights, &params);
+    println!("Log-likelihood: {}", log_likelihood);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+    use crate::likelihood::variant_log_likelihood;
+    use crate::params::Params;
+
+    #[test]
+    fn single_trait_is_univariate_normal() {
+        let trait_names = Arc::new(vec!["a".to_string()]);
+        let params =
+            Params { trait_names, mu: 0.7, tau: 0.5, betas: vec![2.0], sigmas: vec![0.3] };
+        let (o, se): (f64, f64) = (1.1, 0.2);
+        let variance = (2.0f64 * 0.5).powi(2) + 0.3f64.powi(2) + se.powi(2);
+        let expected =
+            -0.5 * ((2.0 * PI * variance).ln() + (o - 2.0 * 0.7f64).powi(2) / variance);
+        let actual = variant_log_likelihood(&params, &[o], &[se]);
+        assert!((actual - expected).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use crate::check::check_config;
+use crate::data::{GwasData, load_data, Weights};
+use crate::error::Error;
+use crate::likelihood::log_likelihood;
+use crate::math::special::chi_squared_survival;
+use crate::options::action::Action;
+use crate::options::cli::CompareOptions;
+use crate::options::config::load_config;
+use crate::params::read_params_from_file;
+use crate::train::param_meta_stats::str18;
+
+struct ModelFit {
+    params_file: String,
+    trait_names: Arc<Vec<String>>,
+    log_likelihood: f64,
+    n_free_params: usize,
+    aic: f64,
+    bic: f64,
+}
+
+const N_ITERATIONS_SIGMA_FIT_MAX: usize = 1000;
+const TOLERANCE_SIGMA_FIT: f64 = 1e-9;
+
+//  The model is invariant under rescaling E (mu, tau and the betas), so that is one parameter
+//  less than mu, tau and a beta and sigma per trait. Traits not loading on E add their sigma.
+fn n_free_params(n_traits: usize, n_traits_unloaded: usize) -> usize {
+    2 * n_traits + 1 + n_traits_unloaded
+}
+
+pub(crate) fn fit_unloaded_sigma(data: &GwasData, weights: &Weights, i_trait: usize) -> f64 {
+    //  EM for the variance of a trait that does not load on E, so its true effect is N(0, sigma^2)
+    let js: Vec<usize> =
+        (0..data.n_data_points()).filter(|&j| {
+            data.betas[j][i_trait].is_finite() && data.ses[j][i_trait].is_finite()
+        }).collect();
+    let weight_sum: f64 = js.iter().map(|&j| weights.weights[j]).sum();
+    let mut var: f64 =
+        js.iter().map(|&j| {
+            weights.weights[j] * data.betas[j][i_trait].powi(2)
+        }).sum::<f64>() / weight_sum;
+    for _ in 0..N_ITERATIONS_SIGMA_FIT_MAX {
+        let var_new = js.iter().map(|&j| {
+            let o = data.betas[j][i_trait];
+            let var_o = data.ses[j][i_trait].powi(2);
+            let var_post = 1.0 / (1.0 / var + 1.0 / var_o);
+            let mean_post = var_post * o / var_o;
+            weights.weights[j] * (var_post + mean_post.powi(2))
+        }).sum::<f64>() / weight_sum;
+        let is_converged = (var_new - var).abs() <= TOLERANCE_SIGMA_FIT * var;
+        var = var_new;
+        if is_converged {
+            break
+        }
+    }
+    var.sqrt()
+}
+
+fn is_nested_in(trait_names: &[String], trait_names_outer: &[String]) -> bool {
+    trait_names.len() < trait_names_outer.len()
+        && trait_names.iter().all(|trait_name| trait_names_outer.contains(trait_name))
+}
+
+pub(crate) fn compare_models(options: &CompareOptions) -> Result<(), Error> {
+    let config = load_config(&options.config_file)?;
+    check_config(&config)?;
+    let data = load_data(&config, Action::Train)?;
+    let trait_names = data.gwas_data.meta.trait_names.clone();
+    let n_effective = data.weights.sum;
+    println!("Loaded data for {} variants", data.gwas_data.meta.n_data_points());
+    println!("Traits not in a model are evaluated as not loading on E (beta zero), with fitted \
+    sigma.");
+    let sigmas_unloaded: Vec<f64> =
+        (0..trait_names.len()).map(|i_trait| {
+            fit_unloaded_sigma(&data.gwas_data, &data.weights, i_trait)
+        }).collect();
+    let mut fits: Vec<ModelFit> = Vec::with_capacity(options.params_files.len());
+    for params_file in &options.params_files {
+        let params = read_params_from_file(params_file)?;
+        let mut params_extended = params.extended_to(trait_names.clone())?;
+        let mut n_traits_unloaded: usize = 0;
+        for (i_trait, trait_name) in trait_names.iter().enumerate() {
+            if !params.trait_names.contains(trait_name) {
+                params_extended.sigmas[i_trait] = sigmas_unloaded[i_trait];
+                n_traits_unloaded += 1;
+            }
+        }
+        let log_likelihood =
+            log_likelihood(&data.gwas_data, &data.weights, &params_extended);
+        let n_free_params = n_free_params(params.n_traits(), n_traits_unloaded);
+        let k = n_free_params as f64;
+        let aic = 2.0 * k - 2.0 * log_likelihood;
+        let bic = k * n_effective.ln() - 2.0 * log_likelihood;
+        let params_file = params_file.clone();
+        let trait_names = params.trait_names.clone();
+        fits.push(ModelFit { params_file, trait_names, log_likelihood, n_free_params, aic, bic })
+    }
+    println!("{} {} {} {} {} {}", str18("model"), str18("traits"), str18("loglik"),
+             str18("n_params"), str18("aic"), str18("bic"));
+    for (i_model, fit) in fits.iter().enumerate() {
+        println!("{} {} {} {} {} {}", str18(i_model), str18(fit.trait_names.len()),
+                 str18(fit.log_likelihood), str18(fit.n_free_params), str18(fit.aic),
+                 str18(fit.bic));
+    }
+    for (i_model, fit) in fits.iter().enumerate() {
+        println!("Model {}: {} ({})", i_model, fit.params_file, fit.trait_names.join(", "));
+    }
+    if let Some(best) = fits.iter().enumerate().min_by(|(_, a), (_, b)| a.bic.total_cmp(&b.bic)) {
+        println!("Lowest BIC: model {}", best.0);
+    }
+    let mut any_nested = false;
+    for (i_inner, inner) in fits.iter().enumerate() {
+        for (i_outer, outer) in fits.iter().enumerate() {
+            if is_nested_in(&inner.trait_names, &outer.trait_names) {
+                if !any_nested {
+                    println!("Likelihood-ratio tests for nested models:");
+                    println!("{} {} {} {} {}", str18("null"), str18("alternative"),
+                             str18("statistic"), str18("df"), str18("p"));
+                    any_nested = true;
+                }
+                let statistic = 2.0 * (outer.log_likelihood - inner.log_likelihood);
+                let df = outer.n_free_params - inner.n_free_params;
+                let p = chi_squared_survival(statistic.max(0.0), df as f64);
+                println!("{} {} {} {} {}", str18(i_inner), str18(i_outer), str18(statistic),
+                         str18(df), str18(p));
+            }
+        }
+    }
+    if !any_nested {
+        println!("No nested models, so no likelihood-ratio tests.")
+    }
+    Ok(())
+}
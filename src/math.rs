@@ -2,4 +2,7 @@ pub(crate) mod matrix;
 pub(crate) mod stats;
 pub(crate) mod skip_stats;
 pub(crate) mod trident;
-mod snapped_stats;
\ No newline at end of file
+pub(crate) mod special;
+pub(crate) mod p_square;
+pub(crate) mod batch_means;
+pub(crate) mod tally;
\ No newline at end of file
@@ -0,0 +1,108 @@
+use crate::math::tally::Tally;
+
+//  Monte Carlo standard error of the mean of an autocorrelated chain by non-overlapping batch
+//  means, with about sqrt(n) batches of about sqrt(n) values each. Streaming: whenever there are
+//  twice as many batches as values per batch, neighbouring batches are merged, so only the batch
+//  sums are kept.
+
+pub(crate) struct BatchMeans {
+    tally: Tally,
+    batch_size: usize,
+    batch_sums: Vec<f64>,
+    batch_sum_current: f64,
+    n_current: usize,
+    first: f64,
+    previous: f64,
+    lag1_product_sum: f64,
+}
+
+impl BatchMeans {
+    pub(crate) fn new() -> BatchMeans {
+        let tally = Tally::new();
+        let batch_size: usize = 1;
+        let batch_sums: Vec<f64> = Vec::new();
+        let batch_sum_current: f64 = 0.0;
+        let n_current: usize = 0;
+        let first: f64 = f64::NAN;
+        let previous: f64 = f64::NAN;
+        let lag1_product_sum: f64 = 0.0;
+        BatchMeans {
+            tally, batch_size, batch_sums, batch_sum_current, n_current, first, previous,
+            lag1_product_sum
+        }
+    }
+    pub(crate) fn add(&mut self, value: f64) {
+        if self.tally.n == 0 {
+            self.first = value;
+        } else {
+            self.lag1_product_sum += self.previous * value;
+        }
+        self.previous = value;
+        self.tally.add(value);
+        self.batch_sum_current += value;
+        self.n_current += 1;
+        if self.n_current == self.batch_size {
+            self.batch_sums.push(self.batch_sum_current);
+            self.batch_sum_current = 0.0;
+            self.n_current = 0;
+            if self.batch_sums.len() >= 2 * self.batch_size {
+                self.batch_sums =
+                    self.batch_sums.chunks_exact(2).map(|pair| pair[0] + pair[1]).collect();
+                self.batch_size *= 2;
+            }
+        }
+    }
+    pub(crate) fn n(&self) -> usize { self.tally.n as usize }
+    pub(crate) fn autocorrelation_lag1(&self) -> f64 {
+        let n_f = self.tally.n as f64;
+        let mean = self.tally.mean();
+        let sum = n_f * mean;
+        let covariance_sum =
+            self.lag1_product_sum - mean * (2.0 * sum - self.first - self.previous)
+                + (n_f - 1.0) * mean.powi(2);
+        covariance_sum / self.tally.m2
+    }
+    pub(crate) fn mcse(&self) -> f64 {
+        let n_batches = self.batch_sums.len();
+        if self.batch_size < 2 || n_batches < 2 {
+            return f64::INFINITY
+        }
+        let batch_size_f = self.batch_size as f64;
+        let n_batches_f = n_batches as f64;
+        let mean = self.batch_sums.iter().sum::<f64>() / (batch_size_f * n_batches_f);
+        let variance =
+            self.batch_sums.iter().map(|batch_sum| (batch_sum / batch_size_f - mean).powi(2))
+                .sum::<f64>() / (n_batches_f - 1.0);
+        (variance / n_batches_f).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rand_distr::{Distribution, StandardNormal};
+    use crate::math::batch_means::BatchMeans;
+
+    #[test]
+    fn ar1_chain() {
+        //  AR(1) with coefficient phi has lag-1 autocorrelation phi and MCSE of the mean
+        //  sqrt((1 + phi) / (1 - phi) / n) for unit stationary variance.
+        let phi: f64 = 0.5;
+        let n: usize = 200000;
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut batch_means = BatchMeans::new();
+        let mut x: f64 = 0.0;
+        for _ in 0..n {
+            let z: f64 = StandardNormal.sample(&mut rng);
+            x = phi * x + (1.0 - phi.powi(2)).sqrt() * z;
+            batch_means.add(x);
+        }
+        let mcse_expected = ((1.0 + phi) / (1.0 - phi) / (n as f64)).sqrt();
+        assert_eq!(batch_means.n(), n);
+        let variance = batch_means.tally.m2 / (n as f64 - 1.0);
+        assert!((variance.sqrt() - 1.0).abs() < 0.02);
+        assert!((batch_means.autocorrelation_lag1() - phi).abs() < 0.02);
+        assert!((batch_means.mcse() / mcse_expected - 1.0).abs() < 0.3);
+    }
+}
@@ -1,6 +1,7 @@
 use std::ops::{Index, IndexMut};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Matrix {
     pub(crate) n_cols: usize,
     pub(crate) elements: Vec<f64>,
@@ -21,6 +22,27 @@ impl Matrix {
         };
         Matrix { n_cols, elements }
     }
+    pub(crate) fn n_rows(&self) -> usize { self.elements.len() / self.n_cols }
+    pub(crate) fn cholesky(&self) -> Option<Matrix> {
+        //  Lower triangular L with L*L^T equal to this symmetric matrix, if positive definite
+        let n = self.n_rows();
+        let mut lower = Matrix::fill(n, n, |_, _| 0.0);
+        for i in 0..n {
+            for j in 0..=i {
+                let sum: f64 = (0..j).map(|k| lower[i][k] * lower[j][k]).sum();
+                if i == j {
+                    let diagonal = self[i][i] - sum;
+                    if diagonal <= 0.0 {
+                        return None
+                    }
+                    lower[i][j] = diagonal.sqrt();
+                } else {
+                    lower[i][j] = (self[i][j] - sum) / lower[j][j];
+                }
+            }
+        }
+        Some(lower)
+    }
 }
 
 impl Index<usize> for Matrix {
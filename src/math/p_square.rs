@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+//  P-square algorithm (Jain & Chlamtac, 1985) for estimating a quantile in constant memory.
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct PSquare {
+    p: f64,
+    n: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl PSquare {
+    pub(crate) fn new(p: f64) -> PSquare {
+        let n: usize = 0;
+        let heights = [0.0; 5];
+        let positions = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let desired = [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0];
+        let increments = [0.0, 0.5 * p, p, 0.5 * (1.0 + p), 1.0];
+        PSquare { p, n, heights, positions, desired, increments }
+    }
+    pub(crate) fn add(&mut self, x: f64) {
+        if self.n < 5 {
+            self.heights[self.n] = x;
+            self.n += 1;
+            if self.n == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.n += 1;
+        let k =
+            if x < self.heights[0] {
+                self.heights[0] = x;
+                0
+            } else if x >= self.heights[4] {
+                self.heights[4] = x;
+                3
+            } else {
+                (0..4).find(|&i| x < self.heights[i + 1]).unwrap_or(3)
+            };
+        for position in self.positions.iter_mut().skip(k + 1) {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments.iter()) {
+            *desired += increment;
+        }
+        for i in 1..4 {
+            let d = self.desired[i] - self.positions[i];
+            if (d >= 1.0 && self.positions[i + 1] - self.positions[i] > 1.0)
+                || (d <= -1.0 && self.positions[i - 1] - self.positions[i] < -1.0) {
+                let d = d.signum();
+                let height = self.parabolic(i, d);
+                self.heights[i] =
+                    if self.heights[i - 1] < height && height < self.heights[i + 1] {
+                        height
+                    } else {
+                        self.linear(i, d)
+                    };
+                self.positions[i] += d;
+            }
+        }
+    }
+    fn parabolic(&self, i: usize, d: f64) -> f64 {
+        let q = &self.heights;
+        let n = &self.positions;
+        q[i] + d / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+            + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+    fn linear(&self, i: usize, d: f64) -> f64 {
+        let j = if d > 0.0 { i + 1 } else { i - 1 };
+        self.heights[i]
+            + d * (self.heights[j] - self.heights[i]) / (self.positions[j] - self.positions[i])
+    }
+    pub(crate) fn p(&self) -> f64 { self.p }
+    pub(crate) fn quantile(&self) -> f64 {
+        if self.n >= 5 {
+            self.heights[2]
+        } else if self.n == 0 {
+            f64::NAN
+        } else {
+            let mut values = self.heights[0..self.n].to_vec();
+            values.sort_by(f64::total_cmp);
+            let i = ((self.p * (self.n as f64)).ceil() as usize).clamp(1, self.n) - 1;
+            values[i]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rand_distr::{Distribution, Normal};
+    use crate::math::p_square::PSquare;
+
+    #[test]
+    fn normal_quantiles() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let normal = Normal::new(3.0, 2.0).unwrap();
+        let mut estimators: Vec<PSquare> =
+            [0.025, 0.5, 0.975].iter().map(|&p| PSquare::new(p)).collect();
+        for _ in 0..100000 {
+            let x = normal.sample(&mut rng);
+            estimators.iter_mut().for_each(|estimator| estimator.add(x));
+        }
+        let expected = [3.0 - 1.959964 * 2.0, 3.0, 3.0 + 1.959964 * 2.0];
+        for (estimator, expected) in estimators.iter().zip(expected.iter()) {
+            assert!((estimator.quantile() - expected).abs() < 0.05);
+        }
+    }
+}
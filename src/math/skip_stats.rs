@@ -1,4 +1,6 @@
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct SkipStats {
     pub(crate) n: usize,
     pub(crate) sum: f64,
@@ -38,4 +38,19 @@ impl Stats {
             Some(self.var_sum / (self.n as f64))
         }
     }
+}
+
+pub(crate) fn percentile(sorted: &[f64], p: f64) -> f64 {
+    //  Linear interpolation between closest ranks; expects values sorted in ascending order.
+    match sorted.len() {
+        0 => { f64::NAN }
+        1 => { sorted[0] }
+        n => {
+            let position = p.clamp(0.0, 1.0) * ((n - 1) as f64);
+            let i_lower = position.floor() as usize;
+            let i_upper = position.ceil() as usize;
+            let fraction = position - (i_lower as f64);
+            sorted[i_lower] + fraction * (sorted[i_upper] - sorted[i_lower])
+        }
+    }
 }
\ No newline at end of file
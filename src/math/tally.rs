@@ -0,0 +1,25 @@
+//  Count, mean and sum of squared deviations, updated one value at a time (Welford).
+
+#[derive(Clone)]
+pub(crate) struct Tally {
+    pub(crate) n: u64,
+    mean: f64,
+    pub(crate) m2: f64,
+}
+
+impl Tally {
+    pub(crate) fn new() -> Tally {
+        let n: u64 = 0;
+        let mean: f64 = 0.0;
+        let m2: f64 = 0.0;
+        Tally { n, mean, m2 }
+    }
+    pub(crate) fn add(&mut self, value: f64) {
+        self.n += 1;
+        let delta = value - self.mean;
+        self.mean += delta / (self.n as f64);
+        let delta2 = value - self.mean;
+        self.m2 += delta * delta2;
+    }
+    pub(crate) fn mean(&self) -> f64 { self.mean }
+}
\ No newline at end of file
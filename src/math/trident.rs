@@ -1,7 +1,8 @@
 use std::mem::replace;
+use serde::{Deserialize, Serialize};
 use crate::math::skip_stats::SkipStats;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct TridentStats {
     stats: [SkipStats; 3],
 }
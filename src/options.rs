@@ -2,5 +2,6 @@ pub(crate) mod cli;
 pub(crate) mod action;
 pub(crate) mod config;
 pub(crate) mod check_pre;
+pub(crate) mod shard;
 
 
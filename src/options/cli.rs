@@ -2,12 +2,21 @@ use clap::{Arg, ArgMatches, command, Command};
 use crate::error::Error;
 use crate::options::action;
 use crate::options::action::Action;
+use crate::options::shard::Shard;
 
 mod params {
     pub(crate) const CONFIG_FILE: &str = "conf-file";
     pub(crate) const CONFIG_FILE_SHORT: char = 'f';
     pub(crate) const DRY: &str = "dry";
     pub(crate) const DRY_SHORT: char = 'd';
+    pub(crate) const RESUME: &str = "resume";
+    pub(crate) const RESUME_SHORT: char = 'r';
+    pub(crate) const SHARD: &str = "shard";
+    pub(crate) const SHARD_SHORT: char = 's';
+    pub(crate) const N_SHARDS: &str = "n-shards";
+    pub(crate) const N_SHARDS_SHORT: char = 'n';
+    pub(crate) const SEED: &str = "seed";
+    pub(crate) const SEED_SHORT: char = 'e';
     pub(crate) const PHENET_FILE: &str = "phenet-file";
     pub(crate) const PHENET_FILE_SHORT: char = 'i';
     pub(crate) const PARAMS_FILE: &str = "params-file";
@@ -18,17 +27,33 @@ mod params {
     pub(crate) const SCALE_SHORT: char = 's';
     pub(crate) const OUT_FILE: &str = "out-file";
     pub(crate) const OUT_FILE_SHORT: char = 'o';
+    pub(crate) const N_FOLDS: &str = "n-folds";
+    pub(crate) const N_FOLDS_SHORT: char = 'k';
 }
 
 mod commands {
     pub(crate) const IMPORT_PHENET: &str = "import-phenet";
     pub(crate) const SCALE_SIGMAS: &str = "scale-sigmas";
+    pub(crate) const LOGLIK: &str = "loglik";
+    pub(crate) const COMPARE: &str = "compare";
+    pub(crate) const CV_TRAITS: &str = "cv-traits";
+    pub(crate) const CV_FOLDS: &str = "cv-folds";
+    pub(crate) const MERGE_CLASSIFY: &str = "merge-classify";
+}
+
+//  Training resumes from a checkpoint file, classify from the temp file of its config
+pub(crate) enum Resume {
+    Checkpoint(String),
+    TempFile,
 }
 
 pub struct CoreOptions {
     pub(crate) action: Action,
     pub(crate) config_file: String,
     pub(crate) dry: bool,
+    pub(crate) resume: Option<Resume>,
+    pub(crate) shard: Option<Shard>,
+    pub(crate) seed: Option<u64>,
 }
 
 pub struct ImportPhenetOptions {
@@ -44,10 +69,39 @@ pub(crate) struct ScaleSigmasOptions {
     pub(crate) out_file: String
 }
 
+pub(crate) struct LogLikOptions {
+    pub(crate) config_file: String,
+    pub(crate) params_file: String,
+}
+
+pub(crate) struct CompareOptions {
+    pub(crate) config_file: String,
+    pub(crate) params_files: Vec<String>,
+}
+
+pub(crate) struct CvTraitsOptions {
+    pub(crate) config_file: String,
+}
+
+pub(crate) struct CvFoldsOptions {
+    pub(crate) config_file: String,
+    pub(crate) n_folds: usize,
+}
+
+pub(crate) struct MergeClassifyOptions {
+    pub(crate) config_file: String,
+    pub(crate) n_shards: usize,
+}
+
 pub(crate) enum Choice {
     Core(CoreOptions),
     ImportPhenet(ImportPhenetOptions),
-    ScaleSigmas(ScaleSigmasOptions)
+    ScaleSigmas(ScaleSigmasOptions),
+    LogLik(LogLikOptions),
+    Compare(CompareOptions),
+    CvTraits(CvTraitsOptions),
+    CvFolds(CvFoldsOptions),
+    MergeClassify(MergeClassifyOptions),
 }
 
 fn new_arg(name: &'static str, short: char) -> Arg {
@@ -60,6 +114,22 @@ fn new_action_command(name: &'static str) -> Command {
         .arg(new_arg(params::CONFIG_FILE, params::CONFIG_FILE_SHORT))
         .arg(new_arg(params::DRY, params::DRY_SHORT).num_args(0)
             .action(clap::ArgAction::SetTrue))
+        .arg(new_arg(params::SEED, params::SEED_SHORT)
+            .value_parser(clap::value_parser!(u64)))
+}
+
+fn new_train_command() -> Command {
+    //  Resumes from the checkpoint file given
+    new_action_command(action::names::TRAIN)
+        .arg(new_arg(params::RESUME, params::RESUME_SHORT))
+}
+
+fn new_classify_command() -> Command {
+    //  Resumes from the temp file of the config
+    new_action_command(action::names::CLASSIFY)
+        .arg(new_arg(params::RESUME, params::RESUME_SHORT).num_args(0)
+            .action(clap::ArgAction::SetTrue))
+        .arg(new_arg(params::SHARD, params::SHARD_SHORT))
 }
 
 fn new_import_phenet_command() -> Command {
@@ -78,6 +148,38 @@ fn new_scale_sigmas_command() -> Command {
         .arg(new_arg(params::OUT_FILE, params::OUT_FILE_SHORT))
 }
 
+fn new_loglik_command() -> Command {
+    Command::new(commands::LOGLIK)
+        .arg(new_arg(params::CONFIG_FILE, params::CONFIG_FILE_SHORT))
+        .arg(new_arg(params::PARAMS_FILE, params::PARAMS_FILE_SHORT))
+}
+
+fn new_compare_command() -> Command {
+    Command::new(commands::COMPARE)
+        .arg(new_arg(params::CONFIG_FILE, params::CONFIG_FILE_SHORT))
+        .arg(new_arg(params::PARAMS_FILE, params::PARAMS_FILE_SHORT)
+            .action(clap::ArgAction::Append))
+}
+
+fn new_cv_traits_command() -> Command {
+    Command::new(commands::CV_TRAITS)
+        .arg(new_arg(params::CONFIG_FILE, params::CONFIG_FILE_SHORT))
+}
+
+fn new_cv_folds_command() -> Command {
+    Command::new(commands::CV_FOLDS)
+        .arg(new_arg(params::CONFIG_FILE, params::CONFIG_FILE_SHORT))
+        .arg(new_arg(params::N_FOLDS, params::N_FOLDS_SHORT)
+            .value_parser(clap::value_parser!(usize)))
+}
+
+fn new_merge_classify_command() -> Command {
+    Command::new(commands::MERGE_CLASSIFY)
+        .arg(new_arg(params::CONFIG_FILE, params::CONFIG_FILE_SHORT))
+        .arg(new_arg(params::N_SHARDS, params::N_SHARDS_SHORT)
+            .value_parser(clap::value_parser!(usize)))
+}
+
 fn missing_option_error(name: &str, long: &str, short: char) -> Error {
     Error::from(format!("Missing {} option ('--{}' or '-{}').", name, long, short))
 }
@@ -89,7 +191,23 @@ fn get_core_options(action: Action, sub_matches: &ArgMatches) -> Result<CoreOpti
                                  params::CONFIG_FILE_SHORT)
         })?;
     let dry = sub_matches.get_flag(params::DRY);
-    Ok(CoreOptions { action, config_file, dry })
+    let (resume, shard) =
+        match action {
+            Action::Train => {
+                let resume =
+                    sub_matches.get_one::<String>(params::RESUME).cloned().map(Resume::Checkpoint);
+                (resume, None)
+            }
+            Action::Classify => {
+                let resume = sub_matches.get_flag(params::RESUME).then_some(Resume::TempFile);
+                let shard =
+                    sub_matches.get_one::<String>(params::SHARD)
+                        .map(|shard| shard.parse::<Shard>()).transpose()?;
+                (resume, shard)
+            }
+        };
+    let seed = sub_matches.get_one::<u64>(params::SEED).cloned();
+    Ok(CoreOptions { action, config_file, dry, resume, shard, seed })
 }
 
 fn get_import_phenet_options(sub_matches: &ArgMatches) -> Result<ImportPhenetOptions, Error> {
@@ -135,19 +253,95 @@ fn get_scale_sigmas_options(sub_matches: &ArgMatches) -> Result<ScaleSigmasOptio
     Ok(ScaleSigmasOptions { in_file, scale, out_file })
 }
 
+fn get_loglik_options(sub_matches: &ArgMatches) -> Result<LogLikOptions, Error> {
+    let config_file =
+        sub_matches.get_one::<String>(params::CONFIG_FILE).cloned().ok_or_else(|| {
+            missing_option_error("config file", params::CONFIG_FILE,
+                                 params::CONFIG_FILE_SHORT)
+        })?;
+    let params_file =
+        sub_matches.get_one::<String>(params::PARAMS_FILE).cloned().ok_or_else(|| {
+            missing_option_error("params file", params::PARAMS_FILE,
+                                 params::PARAMS_FILE_SHORT)
+        })?;
+    Ok(LogLikOptions { config_file, params_file })
+}
+
+fn get_compare_options(sub_matches: &ArgMatches) -> Result<CompareOptions, Error> {
+    let config_file =
+        sub_matches.get_one::<String>(params::CONFIG_FILE).cloned().ok_or_else(|| {
+            missing_option_error("config file", params::CONFIG_FILE,
+                                 params::CONFIG_FILE_SHORT)
+        })?;
+    let params_files: Vec<String> =
+        sub_matches.get_many::<String>(params::PARAMS_FILE).map(|values| {
+            values.cloned().collect()
+        }).unwrap_or_default();
+    if params_files.len() < 2 {
+        Err(Error::from(format!("Need at least two params files ('--{}' or '-{}').",
+                                params::PARAMS_FILE, params::PARAMS_FILE_SHORT)))?;
+    }
+    Ok(CompareOptions { config_file, params_files })
+}
+
+fn get_cv_traits_options(sub_matches: &ArgMatches) -> Result<CvTraitsOptions, Error> {
+    let config_file =
+        sub_matches.get_one::<String>(params::CONFIG_FILE).cloned().ok_or_else(|| {
+            missing_option_error("config file", params::CONFIG_FILE,
+                                 params::CONFIG_FILE_SHORT)
+        })?;
+    Ok(CvTraitsOptions { config_file })
+}
+
+fn get_cv_folds_options(sub_matches: &ArgMatches) -> Result<CvFoldsOptions, Error> {
+    let config_file =
+        sub_matches.get_one::<String>(params::CONFIG_FILE).cloned().ok_or_else(|| {
+            missing_option_error("config file", params::CONFIG_FILE,
+                                 params::CONFIG_FILE_SHORT)
+        })?;
+    let n_folds =
+        sub_matches.get_one::<usize>(params::N_FOLDS).cloned().ok_or_else(|| {
+            missing_option_error("number of folds", params::N_FOLDS,
+                                 params::N_FOLDS_SHORT)
+        })?;
+    Ok(CvFoldsOptions { config_file, n_folds })
+}
+
+fn get_merge_classify_options(sub_matches: &ArgMatches)
+                              -> Result<MergeClassifyOptions, Error> {
+    let config_file =
+        sub_matches.get_one::<String>(params::CONFIG_FILE).cloned().ok_or_else(|| {
+            missing_option_error("config file", params::CONFIG_FILE,
+                                 params::CONFIG_FILE_SHORT)
+        })?;
+    let n_shards =
+        sub_matches.get_one::<usize>(params::N_SHARDS).cloned().ok_or_else(|| {
+            missing_option_error("number of shards", params::N_SHARDS,
+                                 params::N_SHARDS_SHORT)
+        })?;
+    Ok(MergeClassifyOptions { config_file, n_shards })
+}
+
 fn known_subcommands_message() -> String {
-    format!("Known subcommands are {}, {} and {}.", action::names::TRAIN, action::names::CLASSIFY,
-            commands::IMPORT_PHENET)
+    format!("Known subcommands are {}, {}, {}, {}, {}, {}, {}, {} and {}.", action::names::TRAIN,
+            action::names::CLASSIFY, commands::IMPORT_PHENET, commands::SCALE_SIGMAS,
+            commands::LOGLIK, commands::COMPARE, commands::CV_TRAITS, commands::CV_FOLDS,
+            commands::MERGE_CLASSIFY)
 }
 
 pub(crate) fn get_choice() -> Result<Choice, Error> {
     let matches = command!()
         .subcommand_required(true)
         .arg_required_else_help(true)
-        .subcommand(new_action_command(action::names::TRAIN))
-        .subcommand(new_action_command(action::names::CLASSIFY))
+        .subcommand(new_train_command())
+        .subcommand(new_classify_command())
         .subcommand(new_import_phenet_command())
         .subcommand(new_scale_sigmas_command())
+        .subcommand(new_loglik_command())
+        .subcommand(new_compare_command())
+        .subcommand(new_cv_traits_command())
+        .subcommand(new_cv_folds_command())
+        .subcommand(new_merge_classify_command())
         .get_matches();
     match matches.subcommand() {
         Some((action::names::TRAIN, sub_matches)) => {
@@ -166,6 +360,26 @@ pub(crate) fn get_choice() -> Result<Choice, Error> {
             let scale_sigmas_options = get_scale_sigmas_options(sub_matches)?;
             Ok(Choice::ScaleSigmas(scale_sigmas_options))
         }
+        Some((commands::LOGLIK, sub_matches)) => {
+            let loglik_options = get_loglik_options(sub_matches)?;
+            Ok(Choice::LogLik(loglik_options))
+        }
+        Some((commands::COMPARE, sub_matches)) => {
+            let compare_options = get_compare_options(sub_matches)?;
+            Ok(Choice::Compare(compare_options))
+        }
+        Some((commands::CV_TRAITS, sub_matches)) => {
+            let cv_traits_options = get_cv_traits_options(sub_matches)?;
+            Ok(Choice::CvTraits(cv_traits_options))
+        }
+        Some((commands::CV_FOLDS, sub_matches)) => {
+            let cv_folds_options = get_cv_folds_options(sub_matches)?;
+            Ok(Choice::CvFolds(cv_folds_options))
+        }
+        Some((commands::MERGE_CLASSIFY, sub_matches)) => {
+            let merge_classify_options = get_merge_classify_options(sub_matches)?;
+            Ok(Choice::MergeClassify(merge_classify_options))
+        }
         Some((subcommand, _)) => {
             Err(Error::from(
                 format!("Unknown subcommand {}. {}", subcommand,
@@ -33,7 +33,48 @@ pub(crate) struct TrainConfig {
     pub(crate) n_iterations_per_round: usize,
     pub(crate) n_rounds: usize,
     pub(crate) normalize_mu_to_one: bool,
-    pub(crate) params_trace_file: Option<String>
+    pub(crate) params_trace_file: Option<String>,
+    pub(crate) bootstrap: Option<BootstrapConfig>,
+    pub(crate) checkpoint: Option<CheckpointConfig>,
+    pub(crate) initial_params_file: Option<String>,
+    pub(crate) n_steps_burn_in_warm_start: Option<usize>,
+    pub(crate) n_starts: Option<usize>,
+    pub(crate) n_chains: Option<usize>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) kernel: Option<KernelType>,
+    pub(crate) rao_blackwell: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum KernelType {
+    Gibbs,
+    Blocked,
+    Metropolis,
+    Slice,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct CheckpointConfig {
+    pub(crate) file: String,
+    pub(crate) n_iterations_between: Option<usize>,
+    pub(crate) include_vars: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct BootstrapConfig {
+    pub(crate) n_replicates: usize,
+    pub(crate) method: Option<BootstrapMethod>,
+    pub(crate) n_iterations: Option<usize>,
+    pub(crate) confidence_level: Option<f64>,
+    pub(crate) out_file: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BootstrapMethod {
+    Resample,
+    Bayesian,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -42,7 +83,44 @@ pub(crate) struct ClassifyConfig {
     pub(crate) n_steps_burn_in: usize,
     pub(crate) n_samples: usize,
     pub(crate) out_file: String,
-    pub(crate) trace_ids: Option<Vec<String>>
+    pub(crate) trace_ids: Option<Vec<String>>,
+    pub(crate) e_quantiles: Option<Vec<f64>>,
+    pub(crate) e_threshold: Option<f64>,
+    pub(crate) method: Option<ClassifyMethod>,
+    pub(crate) models: Option<Vec<ModelConfig>>,
+    pub(crate) param_draws: Option<ParamDrawsConfig>,
+    pub(crate) adaptive: Option<AdaptiveConfig>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) kernel: Option<KernelType>,
+    pub(crate) rao_blackwell: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct AdaptiveConfig {
+    pub(crate) n_samples_min: usize,
+    pub(crate) n_samples_max: usize,
+    pub(crate) block_size: Option<usize>,
+    pub(crate) mcse_fraction: Option<f64>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct ParamDrawsConfig {
+    pub(crate) trace_file: Option<String>,
+    pub(crate) covariance_file: Option<String>,
+    pub(crate) n_draws: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct ModelConfig {
+    pub(crate) name: String,
+    pub(crate) params_file: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ClassifyMethod {
+    Sample,
+    Exact,
 }
 
 pub(crate) fn load_config(file: &str) -> Result<Config, Error> {
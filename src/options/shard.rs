@@ -0,0 +1,41 @@
+use std::ops::Range;
+use std::str::FromStr;
+use crate::error::Error;
+
+//  Shard i of n (counting from 1, like array job task ids), a contiguous range of data points.
+#[derive(Clone, Copy)]
+pub(crate) struct Shard {
+    pub(crate) i_shard: usize,
+    pub(crate) n_shards: usize,
+}
+
+impl Shard {
+    pub(crate) fn new(i_shard: usize, n_shards: usize) -> Result<Shard, Error> {
+        if i_shard == 0 || i_shard > n_shards {
+            Err(Error::from(format!("Shard needs to be from 1 to {}, but is {}.", n_shards,
+                                    i_shard)))?
+        }
+        Ok(Shard { i_shard, n_shards })
+    }
+    pub(crate) fn range(&self, n_data_points: usize) -> Range<usize> {
+        let start = (self.i_shard - 1) * n_data_points / self.n_shards;
+        let end = self.i_shard * n_data_points / self.n_shards;
+        start..end
+    }
+    pub(crate) fn out_file(&self, out_file: &str) -> String {
+        format!("{}_shard_{}_of_{}", out_file, self.i_shard, self.n_shards)
+    }
+}
+
+impl FromStr for Shard {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let parse_error =
+            || Error::from(format!("Shard needs to be like 3/10, but is '{}'.", string));
+        let (i_shard, n_shards) = string.split_once('/').ok_or_else(parse_error)?;
+        let i_shard = i_shard.trim().parse::<usize>().map_err(|_| parse_error())?;
+        let n_shards = n_shards.trim().parse::<usize>().map_err(|_| parse_error())?;
+        Shard::new(i_shard, n_shards)
+    }
+}
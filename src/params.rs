@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 use crate::error::{Error, for_file};
 use std::io::Write;
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub(crate) struct Params {
     pub(crate) trait_names: Arc<Vec<String>>,
     pub(crate) mu: f64,
@@ -83,6 +83,32 @@ impl Params {
             is_cols.iter().map(|i_col| self.sigmas[*i_col]).collect();
         Params { trait_names, mu, tau, betas, sigmas }
     }
+    pub(crate) fn extended_to(&self, trait_names: Arc<Vec<String>>) -> Result<Params, Error> {
+        //  Traits not in these params get beta and sigma zero, i.e. do not load on E.
+        for trait_name in self.trait_names.iter() {
+            if !trait_names.contains(trait_name) {
+                Err(Error::from(format!("Trait {} of params is not among traits {}.",
+                                        trait_name, trait_names.join(", "))))?;
+            }
+        }
+        let mu = self.mu;
+        let tau = self.tau;
+        let mut betas: Vec<f64> = Vec::with_capacity(trait_names.len());
+        let mut sigmas: Vec<f64> = Vec::with_capacity(trait_names.len());
+        for trait_name in trait_names.iter() {
+            match self.trait_names.iter().position(|name| name == trait_name) {
+                None => {
+                    betas.push(0.0);
+                    sigmas.push(0.0);
+                }
+                Some(i_trait) => {
+                    betas.push(self.betas[i_trait]);
+                    sigmas.push(self.sigmas[i_trait]);
+                }
+            }
+        }
+        Ok(Params { trait_names, mu, tau, betas, sigmas })
+    }
     pub(crate) fn plus_overwrite(self, overwrite: &ParamsOverride) -> Params {
         let Params { trait_names, mu, tau, betas, sigmas } = self;
         let mu = overwrite.mu.unwrap_or(mu);
@@ -92,11 +118,20 @@ impl Params {
     pub(crate) fn normalized_with_mu_one(&self) -> Params {
         let trait_names = self.trait_names.clone();
         let mu: f64 = 1.0;
-        let tau = self.tau / self.mu;
+        let tau = self.tau / self.mu.abs();
         let betas: Vec<f64> = self.betas.iter().map( |beta| beta * self.mu).collect();
         let sigmas = self.sigmas.clone();
         Params { trait_names, mu, tau, betas, sigmas }
     }
+    pub(crate) fn rescaled_to_mu(&self, mu: f64) -> Params {
+        //  Same likelihood, with E on the scale given by mu
+        let normalized = self.normalized_with_mu_one();
+        let trait_names = self.trait_names.clone();
+        let tau = normalized.tau * mu.abs();
+        let betas: Vec<f64> = normalized.betas.iter().map(|beta| beta / mu).collect();
+        let sigmas = self.sigmas.clone();
+        Params { trait_names, mu, tau, betas, sigmas }
+    }
 }
 
 impl Index<ParamIndex> for Params {
@@ -141,6 +176,37 @@ pub(crate) fn read_params_from_file(file: &str) -> Result<Params, Error> {
     Ok(params)
 }
 
+//  Params with the seed of the run that trained or used them. Reading params ignores the seed.
+#[derive(Serialize)]
+struct ParamsWithSeed<'a> {
+    #[serde(flatten)]
+    params: &'a Params,
+    seed: u64,
+}
+
+#[derive(Deserialize)]
+struct ParamsWithSeedRead {
+    #[serde(flatten)]
+    params: Params,
+    seed: Option<u64>,
+}
+
+pub(crate) fn read_params_with_seed_from_file(file: &str)
+                                              -> Result<(Params, Option<u64>), Error> {
+    let params_string = for_file(file, read_to_string(file))?;
+    let ParamsWithSeedRead { params, seed } = serde_json::from_str(&params_string)?;
+    Ok((params, seed))
+}
+
+pub(crate) fn write_params_with_seed_to_file(params: &Params, seed: u64, output_file: &str)
+                                             -> Result<(), Error> {
+    let mut writer =
+        BufWriter::new(for_file(output_file, File::create(output_file))?);
+    let json = serde_json::to_string(&ParamsWithSeed { params, seed })?;
+    writeln!(writer, "{}", json)?;
+    Ok(())
+}
+
 pub(crate) fn write_params_to_file(params: &Params, output_file: &str) -> Result<(), Error> {
     let mut writer =
         BufWriter::new(for_file(output_file, File::create(output_file))?);
@@ -148,3 +214,22 @@ pub(crate) fn write_params_to_file(params: &Params, output_file: &str) -> Result
     writeln!(writer, "{}", json)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::params::Params;
+
+    #[test]
+    fn normalized_with_negative_mu() {
+        let trait_names = Arc::new(vec!["a".to_string(), "b".to_string()]);
+        let params =
+            Params {
+                trait_names, mu: -2.0, tau: 1.0, betas: vec![0.5, -1.0], sigmas: vec![0.5, 0.5]
+            };
+        let normalized = params.normalized_with_mu_one();
+        assert_eq!(normalized.mu, 1.0);
+        assert_eq!(normalized.tau, 0.5);
+        assert_eq!(normalized.betas, vec![-1.0, 2.0]);
+    }
+}
@@ -5,7 +5,9 @@ use std::sync::Arc;
 use crate::data::gwas::GwasCols;
 use crate::error::{Error, for_file};
 use crate::options::cli::ImportPhenetOptions;
-use crate::options::config::{ClassifyConfig, Config, FilesConfig, GwasConfig, TrainConfig};
+use crate::options::config::{AdaptiveConfig, BootstrapConfig, CheckpointConfig, ClassifyConfig,
+                             ClassifyMethod, Config, FilesConfig, GwasConfig, KernelType,
+                             ModelConfig, ParamDrawsConfig, TrainConfig};
 use crate::params::{Params, ParamsOverride};
 
 mod defaults {
@@ -204,6 +206,15 @@ impl ConfigBuilder {
         let n_rounds = defaults::train::N_ROUNDS;
         let normalize_mu_to_one = true;
         let params_trace_file: Option<String> = None;
+        let bootstrap: Option<BootstrapConfig> = None;
+        let checkpoint: Option<CheckpointConfig> = None;
+        let initial_params_file: Option<String> = None;
+        let n_steps_burn_in_warm_start: Option<usize> = None;
+        let n_starts: Option<usize> = None;
+        let n_chains: Option<usize> = None;
+        let seed: Option<u64> = None;
+        let kernel: Option<KernelType> = None;
+        let rao_blackwell: Option<bool> = None;
         let train =
             TrainConfig {
                 ids_file,
@@ -212,15 +223,33 @@ impl ConfigBuilder {
                 n_iterations_per_round,
                 n_rounds,
                 normalize_mu_to_one,
-                params_trace_file
+                params_trace_file,
+                bootstrap,
+                checkpoint,
+                initial_params_file,
+                n_steps_burn_in_warm_start,
+                n_starts,
+                n_chains,
+                seed,
+                kernel,
+                rao_blackwell
             };
         let params_override: Option<ParamsOverride> = None;
         let n_steps_burn_in = defaults::classify::N_STEPS_BURN_IN;
         let n_samples = defaults::classify::N_SAMPLES;
         let out_file = options.out_file.clone();
         let trace_ids: Option<Vec<String>> = None;
+        let e_quantiles: Option<Vec<f64>> = None;
+        let e_threshold: Option<f64> = None;
+        let method: Option<ClassifyMethod> = None;
+        let models: Option<Vec<ModelConfig>> = None;
+        let param_draws: Option<ParamDrawsConfig> = None;
+        let adaptive: Option<AdaptiveConfig> = None;
         let classify =
-            ClassifyConfig { params_override, n_steps_burn_in, n_samples, out_file, trace_ids };
+            ClassifyConfig {
+                params_override, n_steps_burn_in, n_samples, out_file, trace_ids, e_quantiles,
+                e_threshold, method, models, param_draws, adaptive, seed, kernel, rao_blackwell
+            };
         Ok(Config { files, gwas, train, classify })
     }
 
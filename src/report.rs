@@ -18,8 +18,8 @@ impl Reporter {
     pub(crate) fn reset_round_timer(&mut self) {
         self.start_time_round = SystemTime::now();
     }
-    pub(crate) fn report(&mut self, summary: &Summary, i_cycle: usize, i_iteration: usize,
-                         n_samples: usize) {
+    pub(crate) fn report(&mut self, summary: &Summary, log_likelihood: f64, i_cycle: usize,
+                         i_iteration: usize, n_samples: usize) {
         let duration_round =
             self.start_time_round.elapsed().unwrap_or(Duration::ZERO);
         let secs_elapsed = (duration_round.as_millis() as f64) / 1000.0;
@@ -32,6 +32,7 @@ impl Reporter {
         which is {} iterations per second and thread. Total time is {}",
                  i_cycle, i_iteration, n_samples, elapsed_round, steps_per_sec,
                  elapsed_total);
+        println!("Log-likelihood: {}", log_likelihood);
         println!("{}", summary);
         self.time_since_last_report = SystemTime::now();
     }
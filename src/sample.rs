@@ -2,4 +2,9 @@ pub(crate) mod sampler;
 pub(crate) mod vars;
 pub(crate) mod var_stats;
 pub(crate) mod trace_file;
+pub(crate) mod conditionals;
+mod blocked;
 mod gibbs;
+mod kernel;
+mod metropolis;
+mod slice;
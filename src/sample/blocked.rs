@@ -0,0 +1,36 @@
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use crate::sample::conditionals::calculate_e_posterior;
+use crate::data::GwasData;
+use crate::params::Params;
+use crate::sample::gibbs::GibbsKernel;
+use crate::sample::kernel::Kernel;
+use crate::sample::vars::Vars;
+
+//  Draws E and T jointly, as E from its posterior given the observations with T integrated out,
+//  followed by each T given E. Unlike the Gibbs kernel, consecutive draws are independent, even
+//  if the sigmas are small compared to tau.
+pub(crate) struct BlockedKernel {
+    gibbs: GibbsKernel,
+}
+
+impl BlockedKernel {
+    pub(crate) fn new() -> BlockedKernel {
+        let gibbs = GibbsKernel::new();
+        BlockedKernel { gibbs }
+    }
+}
+
+impl<R: Rng> Kernel<R> for BlockedKernel {
+    fn update(&mut self, rng: &mut R, data: &GwasData, params: &Params, vars: &mut Vars,
+              i_data_point: usize) {
+        let e_posterior =
+            calculate_e_posterior(params, &data.betas[i_data_point], &data.ses[i_data_point]);
+        vars.es[i_data_point] =
+            Normal::new(e_posterior.mean, e_posterior.variance.sqrt()).unwrap().sample(rng);
+        for i_trait in 0..params.n_traits() {
+            vars.ts[i_data_point][i_trait] =
+                self.gibbs.draw_t(rng, data, vars, params, i_data_point, i_trait);
+        }
+    }
+}
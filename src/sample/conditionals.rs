@@ -0,0 +1,58 @@
+use crate::params::Params;
+
+//  Gaussian conditionals and posterior of E and the Ts, for E ~ N(mu, tau^2),
+//  T_i = beta_i E + N(0, sigma_i^2) and O_i = T_i + N(0, se_i^2).
+
+pub(crate) struct EPosterior {
+    pub(crate) mean: f64,
+    pub(crate) variance: f64,
+}
+
+pub(crate) fn calculate_e_posterior(params: &Params, betas: &[f64], ses: &[f64]) -> EPosterior {
+    let tau2 = params.tau.powi(2);
+    let numerator: f64 =
+        params.betas.iter().zip(params.sigmas.iter()).zip(betas.iter())
+            .zip(ses.iter())
+            .map(|(((&beta, &sigma), &o), &se)| {
+                beta * o / (sigma.powi(2) + se.powi(2))
+            }).sum::<f64>() + params.mu / tau2;
+    let denominator: f64 =
+        params.betas.iter().zip(params.sigmas.iter()).zip(ses.iter())
+            .map(|((&beta, &sigma), &se)| {
+                beta.powi(2) / (sigma.powi(2) + se.powi(2))
+            }).sum::<f64>() + 1.0 / tau2;
+    let mean = numerator / denominator;
+    let variance = 1.0 / denominator;
+    EPosterior { mean, variance }
+}
+
+//  T given E and the observation is normal with mean coeff_e*E + offset.
+pub(crate) struct TConditional {
+    pub(crate) coeff_e: f64,
+    pub(crate) offset: f64,
+    pub(crate) variance: f64,
+}
+
+//  Gaussian of E given the Ts, as drawn by the Gibbs sampler
+pub(crate) fn calculate_e_conditional(params: &Params, ts: &[f64]) -> EPosterior {
+    let tau2 = params.tau.powi(2);
+    let precision: f64 =
+        1.0 / tau2 + params.betas.iter().zip(params.sigmas.iter()).map(|(beta, sigma)| {
+            (beta / sigma).powi(2)
+        }).sum::<f64>();
+    let variance = 1.0 / precision;
+    let numerator: f64 =
+        params.mu / tau2 + params.betas.iter().zip(params.sigmas.iter()).zip(ts.iter())
+            .map(|((beta, sigma), t)| beta * t / sigma.powi(2)).sum::<f64>();
+    let mean = variance * numerator;
+    EPosterior { mean, variance }
+}
+
+pub(crate) fn calculate_t_conditional(beta: f64, sigma: f64, o: f64, se: f64) -> TConditional {
+    let var_e = sigma.powi(2);
+    let var_o = se.powi(2);
+    let variance = 1.0 / (1.0 / var_e + 1.0 / var_o);
+    let coeff_e = variance * beta / var_e;
+    let offset = variance * o / var_o;
+    TConditional { coeff_e, offset, variance }
+}
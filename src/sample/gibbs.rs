@@ -3,32 +3,24 @@ use rand_distr::Normal;
 use crate::params::Params;
 use crate::sample::vars::Vars;
 use rand_distr::Distribution;
+use crate::sample::conditionals::{calculate_e_conditional, EPosterior};
 use crate::data::GwasData;
+use crate::sample::kernel::Kernel;
 
-pub(crate) struct GibbsSampler<R: Rng> {
-    rng: R,
-}
+//  Draws E and then each T from their conjugate Gaussian conditionals.
+pub(crate) struct GibbsKernel {}
 
-impl<R: Rng> GibbsSampler<R> {
-    pub(crate) fn new(rng: R) -> GibbsSampler<R> { GibbsSampler { rng } }
-    pub(crate) fn draw_e(&mut self, vars: &Vars, params: &Params, i_data_point: usize)
-                         -> f64 {
-        let n_traits = params.trait_names.len();
-        let tau = params.tau;
-        let inv_var_sum: f64 = 1.0 / tau.powi(2) + (0..n_traits).map(|i_trait| {
-            (params.betas[i_trait] / params.sigmas[i_trait]).powi(2)
-        }).sum::<f64>();
-        let variance = 1.0 / inv_var_sum;
-        let std_dev = variance.sqrt();
-        let frac_sum = params.mu / tau.powi(2) + (0..n_traits).map(|i_trait| {
-            params.betas[i_trait] * vars.ts[i_data_point][i_trait] / params.sigmas[i_trait].powi(2)
-        }).sum::<f64>();
-        let mean = variance * frac_sum;
-        Normal::new(mean, std_dev).unwrap().sample(&mut self.rng)
+impl GibbsKernel {
+    pub(crate) fn new() -> GibbsKernel { GibbsKernel {} }
+    pub(crate) fn draw_e<R: Rng>(&self, rng: &mut R, vars: &Vars, params: &Params,
+                                 i_data_point: usize) -> f64 {
+        let EPosterior { mean, variance } =
+            calculate_e_conditional(params, &vars.ts[i_data_point]);
+        Normal::new(mean, variance.sqrt()).unwrap().sample(rng)
     }
 
-    pub(crate) fn draw_t(&mut self, data: &GwasData, vars: &Vars, params: &Params,
-                         i_data_point: usize, i_trait: usize) -> f64 {
+    pub(crate) fn draw_t<R: Rng>(&self, rng: &mut R, data: &GwasData, vars: &Vars,
+                                 params: &Params, i_data_point: usize, i_trait: usize) -> f64 {
         let mu_e = params.betas[i_trait] * vars.es[i_data_point];
         let var_e = params.sigmas[i_trait].powi(2);
         let mu_o = data.betas[i_data_point][i_trait];
@@ -36,6 +28,17 @@ impl<R: Rng> GibbsSampler<R> {
         let variance = 1.0 / (1.0 / var_e + 1.0 / var_o);
         let std_dev = variance.sqrt();
         let mean = variance * (mu_e / var_e + mu_o / var_o);
-        Normal::new(mean, std_dev).unwrap().sample(&mut self.rng)
+        Normal::new(mean, std_dev).unwrap().sample(rng)
+    }
+}
+
+impl<R: Rng> Kernel<R> for GibbsKernel {
+    fn update(&mut self, rng: &mut R, data: &GwasData, params: &Params, vars: &mut Vars,
+              i_data_point: usize) {
+        vars.es[i_data_point] = self.draw_e(rng, vars, params, i_data_point);
+        for i_trait in 0..params.n_traits() {
+            vars.ts[i_data_point][i_trait] =
+                self.draw_t(rng, data, vars, params, i_data_point, i_trait);
+        }
     }
 }
@@ -0,0 +1,106 @@
+use rand::Rng;
+use crate::data::{GwasData, Meta};
+use crate::options::config::KernelType;
+use crate::params::Params;
+use crate::sample::blocked::BlockedKernel;
+use crate::sample::gibbs::GibbsKernel;
+use crate::sample::metropolis::MetropolisKernel;
+use crate::sample::slice::SliceKernel;
+use crate::sample::vars::Vars;
+
+//  Updates E and T of one data point, leaving their distribution given the params and the
+//  observations invariant.
+pub(crate) trait Kernel<R: Rng> {
+    fn update(&mut self, rng: &mut R, data: &GwasData, params: &Params, vars: &mut Vars,
+              i_data_point: usize);
+    //  Adapted tuning, if any, so a checkpointed chain continues with it
+    fn tuning(&self) -> Vec<f64> { Vec::new() }
+    fn set_tuning(&mut self, _tuning: &[f64]) {}
+}
+
+pub(crate) fn new_kernel<R: Rng>(kernel_type: KernelType, meta: &Meta) -> Box<dyn Kernel<R>> {
+    match kernel_type {
+        KernelType::Gibbs => { Box::new(GibbsKernel::new()) }
+        KernelType::Blocked => { Box::new(BlockedKernel::new()) }
+        KernelType::Metropolis => {
+            Box::new(MetropolisKernel::new(meta.n_data_points(), meta.n_traits()))
+        }
+        KernelType::Slice => { Box::new(SliceKernel::new()) }
+    }
+}
+
+//  Log of the conditional density of E, up to a constant, for kernels that are not conjugate
+pub(crate) fn log_density_e(params: &Params, vars: &Vars, i_data_point: usize, e: f64) -> f64 {
+    let prior = -0.5 * ((e - params.mu) / params.tau).powi(2);
+    let ts = &vars.ts[i_data_point];
+    (0..params.n_traits()).map(|i_trait| {
+        -0.5 * ((ts[i_trait] - params.betas[i_trait] * e) / params.sigmas[i_trait]).powi(2)
+    }).sum::<f64>() + prior
+}
+
+//  Log of the conditional density of T for one trait, up to a constant
+pub(crate) fn log_density_t(data: &GwasData, params: &Params, vars: &Vars, i_data_point: usize,
+                            i_trait: usize, t: f64) -> f64 {
+    let t_mean = params.betas[i_trait] * vars.es[i_data_point];
+    let beta_observed = data.betas[i_data_point][i_trait];
+    let se = data.ses[i_data_point][i_trait];
+    -0.5 * ((t - t_mean) / params.sigmas[i_trait]).powi(2)
+        - 0.5 * ((beta_observed - t) / se).powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::sample::conditionals::calculate_e_posterior;
+    use crate::data::{GwasData, Meta};
+    use crate::math::batch_means::BatchMeans;
+    use crate::math::matrix::Matrix;
+    use crate::options::config::KernelType;
+    use crate::params::Params;
+    use crate::sample::kernel::new_kernel;
+    use crate::sample::vars::Vars;
+    use crate::util::rng::{new_rng, RngStream, SeededRng};
+
+    #[test]
+    fn kernels_sample_e_posterior() {
+        let trait_names = Arc::new(vec!["a".to_string(), "b".to_string()]);
+        let var_ids = Arc::new(vec!["v".to_string()]);
+        let meta = Meta { trait_names: trait_names.clone(), var_ids };
+        let betas_observed = [1.2, -2.5];
+        let ses = [0.3, 0.4];
+        let data =
+            GwasData {
+                meta: meta.clone(),
+                betas: Matrix::fill(1, 2, |_, i_trait| betas_observed[i_trait]),
+                ses: Matrix::fill(1, 2, |_, i_trait| ses[i_trait]),
+            };
+        let params =
+            Params {
+                trait_names, mu: 1.0, tau: 0.5, betas: vec![1.0, -2.0], sigmas: vec![0.5, 0.7]
+            };
+        let e_posterior = calculate_e_posterior(&params, &betas_observed, &ses);
+        let n_steps: usize = 100000;
+        for kernel_type in [KernelType::Gibbs, KernelType::Blocked, KernelType::Metropolis,
+            KernelType::Slice] {
+            let mut rng = new_rng(5, RngStream::ClassifyVariant(0));
+            let mut kernel = new_kernel::<SeededRng>(kernel_type, &meta);
+            let mut vars = Vars::initial_vars(&data, &params);
+            let mut e_batch_means = BatchMeans::new();
+            let mut e_sum: f64 = 0.0;
+            let mut e2_sum: f64 = 0.0;
+            for i_step in 0..(n_steps + 1000) {
+                kernel.update(&mut rng, &data, &params, &mut vars, 0);
+                if i_step >= 1000 {
+                    e_batch_means.add(vars.es[0]);
+                    e_sum += vars.es[0];
+                    e2_sum += vars.es[0].powi(2);
+                }
+            }
+            let mcse = e_batch_means.mcse();
+            let e_mean = e_sum / (n_steps as f64);
+            assert!((e_mean - e_posterior.mean).abs() < 5.0 * mcse);
+            let variance = e2_sum / (n_steps as f64) - e_mean.powi(2);
+            assert!((variance / e_posterior.variance - 1.0).abs() < 0.05);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use rand::Rng;
+use rand_distr::{Distribution, StandardNormal};
+use crate::data::GwasData;
+use crate::math::matrix::Matrix;
+use crate::params::Params;
+use crate::sample::kernel::{Kernel, log_density_e, log_density_t};
+use crate::sample::vars::Vars;
+
+mod defaults {
+    //  Optimal for one-dimensional random walk proposals
+    pub(crate) const TARGET_ACCEPTANCE: f64 = 0.44;
+    pub(crate) const INITIAL_STEP: f64 = 0.1;
+}
+
+//  Random walk Metropolis on one variable at a time, with step sizes for E and for each trait
+//  adapted towards the target acceptance rate. Step sizes are kept per data point, since
+//  variants can have posteriors of very different scales. The adaptation diminishes with the
+//  number of updates, so the chain still converges to the right distribution.
+pub(crate) struct MetropolisKernel {
+    log_steps: Matrix,
+    n_updates: Vec<usize>,
+}
+
+impl MetropolisKernel {
+    pub(crate) fn new(n_data_points: usize, n_traits: usize) -> MetropolisKernel {
+        let log_steps =
+            Matrix::fill(n_data_points, n_traits + 1, |_, _| defaults::INITIAL_STEP.ln());
+        let n_updates: Vec<usize> = vec![0; n_data_points];
+        MetropolisKernel { log_steps, n_updates }
+    }
+    fn step<R: Rng, F: Fn(f64) -> f64>(&mut self, rng: &mut R, i_data_point: usize,
+                                       i_step: usize, x: f64, log_density: F) -> f64 {
+        let log_steps = &mut self.log_steps[i_data_point];
+        let z: f64 = StandardNormal.sample(rng);
+        let x_new = x + log_steps[i_step].exp() * z;
+        let log_ratio = log_density(x_new) - log_density(x);
+        let is_accepted = log_ratio >= 0.0 || rng.gen::<f64>().ln() < log_ratio;
+        let acceptance = if is_accepted { 1.0 } else { 0.0 };
+        let rate = 1.0 / ((self.n_updates[i_data_point] + 1) as f64).sqrt();
+        log_steps[i_step] += rate * (acceptance - defaults::TARGET_ACCEPTANCE);
+        if is_accepted { x_new } else { x }
+    }
+}
+
+impl<R: Rng> Kernel<R> for MetropolisKernel {
+    fn update(&mut self, rng: &mut R, data: &GwasData, params: &Params, vars: &mut Vars,
+              i_data_point: usize) {
+        let e = vars.es[i_data_point];
+        vars.es[i_data_point] =
+            self.step(rng, i_data_point, 0, e, |e| {
+                log_density_e(params, vars, i_data_point, e)
+            });
+        for i_trait in 0..params.n_traits() {
+            let t = vars.ts[i_data_point][i_trait];
+            vars.ts[i_data_point][i_trait] =
+                self.step(rng, i_data_point, i_trait + 1, t, |t| {
+                    log_density_t(data, params, vars, i_data_point, i_trait, t)
+                });
+        }
+        self.n_updates[i_data_point] += 1;
+    }
+    fn tuning(&self) -> Vec<f64> {
+        //  Log step sizes by data point, followed by the numbers of updates
+        let mut tuning = self.log_steps.elements.clone();
+        tuning.extend(self.n_updates.iter().map(|&n_updates| n_updates as f64));
+        tuning
+    }
+    fn set_tuning(&mut self, tuning: &[f64]) {
+        let n_log_steps = self.log_steps.elements.len();
+        if tuning.len() == n_log_steps + self.n_updates.len() {
+            let (log_steps, n_updates) = tuning.split_at(n_log_steps);
+            self.log_steps.elements = log_steps.to_vec();
+            self.n_updates = n_updates.iter().map(|&n_updates| n_updates as usize).collect();
+        }
+    }
+}
@@ -1,12 +1,23 @@
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use crate::data::{GwasData, Meta};
-use crate::sample::gibbs::GibbsSampler;
+use crate::options::config::KernelType;
 use crate::params::Params;
-use crate::sample::var_stats::VarStats;
-use crate::sample::vars::{VarIndex, Vars};
+use crate::sample::kernel::{Kernel, new_kernel};
+use crate::sample::var_stats::{EOptions, VarStats};
+use crate::sample::vars::Vars;
 
 pub(crate) struct Sampler<R: Rng> {
-    gibbs: GibbsSampler<R>,
+    rng: R,
+    kernel: Box<dyn Kernel<R>>,
+    var_stats: VarStats,
+}
+
+//  What a sampler needs to continue where it left off, e.g. after a checkpoint
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct SamplerState<R> {
+    rng: R,
+    kernel_tuning: Vec<f64>,
     var_stats: VarStats,
 }
 
@@ -15,10 +26,15 @@ pub(crate) trait ETracer {
 }
 
 impl<R: Rng> Sampler<R> {
-    pub(crate) fn new(meta: &Meta, rng: R) -> Sampler<R> {
-        let gibbs = GibbsSampler::new(rng);
-        let var_stats = VarStats::new(meta.clone());
-        Sampler { gibbs, var_stats }
+    pub(crate) fn new(meta: &Meta, rng: R, kernel_type: KernelType, rao_blackwell: bool)
+                      -> Sampler<R> {
+        Sampler::with_e_options(meta, rng, kernel_type, rao_blackwell, &EOptions::default())
+    }
+    pub(crate) fn with_e_options(meta: &Meta, rng: R, kernel_type: KernelType,
+                                 rao_blackwell: bool, e_options: &EOptions) -> Sampler<R> {
+        let kernel = new_kernel(kernel_type, meta);
+        let var_stats = VarStats::with_e_options(meta.clone(), e_options, rao_blackwell);
+        Sampler { rng, kernel, var_stats }
     }
     pub(crate) fn sample_n(&mut self, data: &GwasData, params: &Params, vars: &mut Vars,
                            n_steps: usize, e_tracer: &mut Option<Box<dyn ETracer>>) {
@@ -28,22 +44,30 @@ impl<R: Rng> Sampler<R> {
     }
     pub(crate) fn sample_one(&mut self, data: &GwasData, params: &Params, vars: &mut Vars,
                              e_tracer: &mut Option<Box<dyn ETracer>>) {
-        for i_var in vars.indices() {
-            match i_var {
-                VarIndex::E { i_data_point } => {
-                    let e = self.gibbs.draw_e(vars, params, i_data_point);
-                    if let Some(e_tracer) = e_tracer {
-                        e_tracer.trace_e(e);
-                    }
-                    vars.es[i_data_point] = e;
-                }
-                VarIndex::T { i_data_point, i_trait } => {
-                    vars.ts[i_data_point][i_trait] =
-                        self.gibbs.draw_t(data, vars, params, i_data_point, i_trait);
-                }
+        for i_data_point in 0..vars.meta.n_data_points() {
+            self.kernel.update(&mut self.rng, data, params, vars, i_data_point);
+            if let Some(e_tracer) = e_tracer {
+                e_tracer.trace_e(vars.es[i_data_point]);
             }
         }
-        self.var_stats.add(vars);
+        self.var_stats.add(data, params, vars);
     }
     pub(crate) fn var_stats(&self) -> &VarStats { &self.var_stats }
 }
+
+impl<R: Rng + Clone> Sampler<R> {
+    pub(crate) fn state(&self) -> SamplerState<R> {
+        let rng = self.rng.clone();
+        let kernel_tuning = self.kernel.tuning();
+        let var_stats = self.var_stats.clone();
+        SamplerState { rng, kernel_tuning, var_stats }
+    }
+    pub(crate) fn from_state(meta: &Meta, kernel_type: KernelType, state: SamplerState<R>)
+                             -> Sampler<R> {
+        let SamplerState { rng, kernel_tuning, mut var_stats } = state;
+        let mut kernel = new_kernel(kernel_type, meta);
+        kernel.set_tuning(&kernel_tuning);
+        var_stats.restore_meta(meta.clone());
+        Sampler { rng, kernel, var_stats }
+    }
+}
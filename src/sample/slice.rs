@@ -0,0 +1,62 @@
+use rand::Rng;
+use crate::data::GwasData;
+use crate::params::Params;
+use crate::sample::kernel::{Kernel, log_density_e, log_density_t};
+use crate::sample::vars::Vars;
+
+mod defaults {
+    pub(crate) const WIDTH: f64 = 1.0;
+    pub(crate) const MAX_STEPS_OUT: usize = 32;
+}
+
+//  Univariate slice sampling on one variable at a time, by stepping out and shrinkage (Neal,
+//  2003). Needs no tuning beyond a rough width.
+pub(crate) struct SliceKernel {}
+
+impl SliceKernel {
+    pub(crate) fn new() -> SliceKernel { SliceKernel {} }
+}
+
+fn slice_sample<R: Rng, F: Fn(f64) -> f64>(rng: &mut R, x: f64, log_density: F) -> f64 {
+    let width = defaults::WIDTH;
+    let log_height = log_density(x) + rng.gen::<f64>().ln();
+    let mut lower = x - width * rng.gen::<f64>();
+    let mut upper = lower + width;
+    let mut n_steps_lower = rng.gen_range(0..defaults::MAX_STEPS_OUT);
+    let mut n_steps_upper = defaults::MAX_STEPS_OUT - 1 - n_steps_lower;
+    while n_steps_lower > 0 && log_density(lower) > log_height {
+        lower -= width;
+        n_steps_lower -= 1;
+    }
+    while n_steps_upper > 0 && log_density(upper) > log_height {
+        upper += width;
+        n_steps_upper -= 1;
+    }
+    loop {
+        let x_new = rng.gen_range(lower..upper);
+        if log_density(x_new) > log_height {
+            return x_new
+        }
+        if x_new < x {
+            lower = x_new;
+        } else {
+            upper = x_new;
+        }
+    }
+}
+
+impl<R: Rng> Kernel<R> for SliceKernel {
+    fn update(&mut self, rng: &mut R, data: &GwasData, params: &Params, vars: &mut Vars,
+              i_data_point: usize) {
+        let e = vars.es[i_data_point];
+        vars.es[i_data_point] =
+            slice_sample(rng, e, |e| log_density_e(params, vars, i_data_point, e));
+        for i_trait in 0..params.n_traits() {
+            let t = vars.ts[i_data_point][i_trait];
+            vars.ts[i_data_point][i_trait] =
+                slice_sample(rng, t, |t| {
+                    log_density_t(data, params, vars, i_data_point, i_trait, t)
+                });
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use std::fs::File;
-use std::io::{Write, BufWriter};
+use std::fs::{File, read_to_string};
+use std::io::{BufRead, BufReader, Write, BufWriter};
 use std::path::PathBuf;
-use crate::error::Error;
+use std::sync::Arc;
+use crate::error::{Error, for_file};
 use crate::params::{ParamIndex, Params};
 
 pub(crate) struct ParamTraceFileWriter {
@@ -20,6 +21,15 @@ impl ParamTraceFileWriter {
         writeln!(writer)?;
         Ok(ParamTraceFileWriter { path, index })
     }
+    pub(crate) fn resume(path: PathBuf, index: usize) -> Result<ParamTraceFileWriter, Error> {
+        //  Keeps the header and the first index entries of an earlier run, dropping any written
+        //  after its checkpoint, and appends from there.
+        let content = read_to_string(&path)?;
+        let n_bytes_kept: usize =
+            content.split_inclusive('\n').take(index + 1).map(str::len).sum();
+        File::options().write(true).open(&path)?.set_len(n_bytes_kept as u64)?;
+        Ok(ParamTraceFileWriter { path, index })
+    }
     pub(crate) fn write(&mut self, params: &Params) -> Result<(), Error> {
         self.index += 1;
         let n_traits = params.trait_names.len();
@@ -32,4 +42,32 @@ impl ParamTraceFileWriter {
         writeln!(writer)?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+pub(crate) fn read_param_trace_file(file: &str, trait_names: Arc<Vec<String>>)
+                                    -> Result<Vec<Params>, Error> {
+    let n_traits = trait_names.len();
+    let reader = BufReader::new(for_file(file, File::open(file))?);
+    let mut lines = reader.lines();
+    let header = lines.next().ok_or_else(|| Error::from(format!("{} is empty.", file)))??;
+    let header_expected: Vec<String> =
+        std::iter::once("index".to_string())
+            .chain(ParamIndex::all(n_traits).map(|param_index| param_index.to_string()))
+            .collect();
+    if header.split('\t').ne(header_expected.iter().map(|col| col.as_str())) {
+        Err(Error::from(format!("Header of {} does not match params for {} traits.", file,
+                                n_traits)))?;
+    }
+    let mut params_list: Vec<Params> = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.is_empty() {
+            continue
+        }
+        let values =
+            line.split('\t').skip(1).map(|value| value.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()?;
+        params_list.push(Params::from_vec(&values, trait_names.clone())?);
+    }
+    Ok(params_list)
+}
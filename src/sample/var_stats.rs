@@ -1,9 +1,17 @@
-use crate::data::{Meta, Weights};
+use serde::{Deserialize, Serialize};
+use crate::data::{GwasData, Meta, Weights};
 use crate::math::matrix::Matrix;
+use crate::math::p_square::PSquare;
+use crate::math::special::{normal_cdf, normal_quantile};
 use crate::params::Params;
+use crate::sample::conditionals::{calculate_e_conditional, calculate_e_posterior,
+                                  calculate_t_conditional, TConditional};
 use crate::sample::vars::Vars;
 
+//  The metadata is not serialized, but restored with restore_meta
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct VarStats {
+    #[serde(skip)]
     meta: Meta,
     n: usize,
     e_sums: Vec<f64>,
@@ -11,16 +19,38 @@ pub(crate) struct VarStats {
     e_t_sums: Matrix,
     t_sums: Matrix,
     t2_sums: Matrix,
+    e_quantiles: Vec<PSquare>,
+    e_threshold: Option<f64>,
+    p_pos_sum: f64,
+    p_neg_sum: f64,
+    p_above_threshold_sum: f64,
+    rao_blackwell: bool,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct EOptions {
+    pub(crate) quantile_probs: Vec<f64>,
+    pub(crate) threshold: Option<f64>,
 }
 
 pub(crate) struct SampledClassification {
     pub(crate) e_mean: f64,
     pub(crate) e_std: f64,
+    pub(crate) e_quantiles: Vec<f64>,
+    pub(crate) p_pos: f64,
+    pub(crate) p_neg: f64,
+    pub(crate) p_above_threshold: Option<f64>,
+    pub(crate) lfsr: f64,
     pub(crate) t_means: Vec<f64>,
+    pub(crate) t_stds: Vec<f64>,
 }
 
 impl VarStats {
     pub(crate) fn new(meta: Meta) -> VarStats {
+        VarStats::with_e_options(meta, &EOptions::default(), false)
+    }
+    pub(crate) fn with_e_options(meta: Meta, e_options: &EOptions, rao_blackwell: bool)
+                                 -> VarStats {
         let n: usize = 0;
         let n_data_points = meta.n_data_points();
         let n_traits = meta.n_traits();
@@ -29,21 +59,111 @@ impl VarStats {
         let e_t_sums: Matrix = Matrix::fill(n_data_points, n_traits, |_, _| 0.0);
         let t_sums: Matrix = Matrix::fill(n_data_points, n_traits, |_, _| 0.0);
         let t2_sums: Matrix = Matrix::fill(n_data_points, n_traits, |_, _| 0.0);
-        VarStats { meta, n, e_sums, e2_sums, e_t_sums, t_sums, t2_sums }
+        let e_quantiles: Vec<PSquare> =
+            e_options.quantile_probs.iter().map(|&p| PSquare::new(p)).collect();
+        let e_threshold = e_options.threshold;
+        let p_pos_sum: f64 = 0.0;
+        let p_neg_sum: f64 = 0.0;
+        let p_above_threshold_sum: f64 = 0.0;
+        VarStats {
+            meta, n, e_sums, e2_sums, e_t_sums, t_sums, t2_sums, e_quantiles, e_threshold,
+            p_pos_sum, p_neg_sum, p_above_threshold_sum, rao_blackwell
+        }
+    }
+    pub(crate) fn restore_meta(&mut self, meta: Meta) { self.meta = meta }
+    pub(crate) fn add(&mut self, data: &GwasData, params: &Params, vars: &Vars) {
+        self.n += 1;
+        let n_data_points = self.meta.n_data_points();
+        for j in 0..n_data_points {
+            if self.rao_blackwell {
+                self.add_conditional(data, params, vars, j)
+            } else {
+                let e_j = vars.es[j];
+                for e_quantile in self.e_quantiles.iter_mut() {
+                    e_quantile.add(e_j)
+                }
+                self.add_sampled(vars, j)
+            }
+        }
+    }
+    //  The per-step value whose average estimates the E mean: the sampled E, or with
+    //  Rao-Blackwellization the mean of E given the sampled Ts. MCSE and autocorrelation of the
+    //  E mean estimate are measured on this, but its spread is not the posterior SD of E.
+    pub(crate) fn e_estimate(&self, params: &Params, vars: &Vars, j: usize) -> f64 {
+        if self.rao_blackwell {
+            calculate_e_conditional(params, &vars.ts[j]).mean
+        } else {
+            vars.es[j]
+        }
+    }
+    fn add_sampled(&mut self, vars: &Vars, j: usize) {
+        let e_j = vars.es[j];
+        if e_j > 0.0 {
+            self.p_pos_sum += 1.0;
+        } else if e_j < 0.0 {
+            self.p_neg_sum += 1.0;
+        }
+        if let Some(e_threshold) = self.e_threshold {
+            if e_j.abs() > e_threshold {
+                self.p_above_threshold_sum += 1.0;
+            }
+        }
+        self.e_sums[j] += e_j;
+        self.e2_sums[j] += e_j.powi(2);
+        for i in 0..self.meta.n_traits() {
+            let t_j_i = vars.ts[j][i];
+            self.e_t_sums[j][i] += e_j * t_j_i;
+            self.t_sums[j][i] += t_j_i;
+            self.t2_sums[j][i] += t_j_i.powi(2);
+        }
     }
-    pub(crate) fn add(&mut self, vars: &Vars) {
+    fn add_conditional(&mut self, data: &GwasData, params: &Params, vars: &Vars, j: usize) {
+        //  Rao-Blackwellized: expectations of E given the sampled Ts, and of T given the
+        //  sampled E, in place of the sampled values themselves.
+        let e_j = vars.es[j];
+        let e_conditional = calculate_e_conditional(params, &vars.ts[j]);
+        let mean_e_j = e_conditional.mean;
+        let std_dev_e_j = e_conditional.variance.sqrt();
+        self.p_pos_sum += normal_cdf(mean_e_j / std_dev_e_j);
+        self.p_neg_sum += normal_cdf(-mean_e_j / std_dev_e_j);
+        if let Some(e_threshold) = self.e_threshold {
+            self.p_above_threshold_sum +=
+                normal_cdf((-e_threshold - mean_e_j) / std_dev_e_j)
+                    + normal_cdf((mean_e_j - e_threshold) / std_dev_e_j);
+        }
+        self.e_sums[j] += mean_e_j;
+        self.e2_sums[j] += e_conditional.variance + mean_e_j.powi(2);
+        for i in 0..self.meta.n_traits() {
+            let TConditional { coeff_e, offset, variance } =
+                calculate_t_conditional(params.betas[i], params.sigmas[i], data.betas[j][i],
+                                        data.ses[j][i]);
+            let mean_t_j_i = coeff_e * e_j + offset;
+            self.e_t_sums[j][i] += e_j * mean_t_j_i;
+            self.t_sums[j][i] += mean_t_j_i;
+            self.t2_sums[j][i] += variance + mean_t_j_i.powi(2);
+        }
+    }
+    pub(crate) fn add_exact(&mut self, data: &GwasData, params: &Params) {
+        //  Adds the exact posterior expectations instead of a sample, as in an EM E-step.
         self.n += 1;
         let n_data_points = self.meta.n_data_points();
         let n_traits = self.meta.n_traits();
         for j in 0..n_data_points {
-            let e_j = vars.es[j];
-            self.e_sums[j] += e_j;
-            self.e2_sums[j] += e_j.powi(2);
+            let betas_j = &data.betas[j];
+            let ses_j = &data.ses[j];
+            let e_posterior = calculate_e_posterior(params, betas_j, ses_j);
+            let mean_e_j = e_posterior.mean;
+            let mean_e2_j = e_posterior.variance + mean_e_j.powi(2);
+            self.e_sums[j] += mean_e_j;
+            self.e2_sums[j] += mean_e2_j;
             for i in 0..n_traits {
-                let t_j_i = vars.ts[j][i];
-                self.e_t_sums[j][i] += e_j * t_j_i;
-                self.t_sums[j][i] += t_j_i;
-                self.t2_sums[j][i] += t_j_i.powi(2);
+                let TConditional { coeff_e, offset, variance } =
+                    calculate_t_conditional(params.betas[i], params.sigmas[i], betas_j[i],
+                                            ses_j[i]);
+                self.e_t_sums[j][i] += coeff_e * mean_e2_j + offset * mean_e_j;
+                self.t_sums[j][i] += coeff_e * mean_e_j + offset;
+                self.t2_sums[j][i] += variance + coeff_e.powi(2) * mean_e2_j
+                    + 2.0 * coeff_e * offset * mean_e_j + offset.powi(2);
             }
         }
     }
@@ -101,14 +221,35 @@ impl VarStats {
         let mut e_mean: f64 = 0.0;
         let mut e2_mean: f64 = 0.0;
         let mut t_means: Vec<f64> = vec![0.0; n_traits];
+        let mut t2_means: Vec<f64> = vec![0.0; n_traits];
         for j in 0..n_data_points {
             e_mean += self.e_sums[j] / denom;
             e2_mean += self.e2_sums[j] / denom;
-            for (i, t_mean) in t_means.iter_mut().enumerate() {
-                *t_mean += self.t_sums[j][i] / denom;
+            for i in 0..n_traits {
+                t_means[i] += self.t_sums[j][i] / denom;
+                t2_means[i] += self.t2_sums[j][i] / denom;
             }
         }
         let e_std = (e2_mean - e_mean.powi(2)).sqrt();
-        SampledClassification { e_mean, e_std, t_means }
+        let t_stds: Vec<f64> =
+            t_means.iter().zip(t2_means.iter())
+                .map(|(t_mean, t2_mean)| (t2_mean - t_mean.powi(2)).max(0.0).sqrt()).collect();
+        let e_quantiles: Vec<f64> =
+            if self.rao_blackwell {
+                //  The E posterior is Gaussian under the model, so its quantiles follow from
+                //  the Rao-Blackwellized mean and standard deviation.
+                self.e_quantiles.iter().map(|e_quantile| {
+                    e_mean + e_std * normal_quantile(e_quantile.p())
+                }).collect()
+            } else {
+                self.e_quantiles.iter().map(|e_quantile| e_quantile.quantile()).collect()
+            };
+        let p_pos = self.p_pos_sum / denom;
+        let p_neg = self.p_neg_sum / denom;
+        let p_above_threshold = self.e_threshold.map(|_| self.p_above_threshold_sum / denom);
+        let lfsr = (1.0 - p_pos).min(1.0 - p_neg);
+        SampledClassification {
+            e_mean, e_std, e_quantiles, p_pos, p_neg, p_above_threshold, lfsr, t_means, t_stds
+        }
     }
 }
\ No newline at end of file
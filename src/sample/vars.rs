@@ -1,4 +1,4 @@
-use std::iter;
+use serde::{Deserialize, Serialize};
 use crate::data::{GwasData, Meta};
 use crate::math::matrix::Matrix;
 use crate::params::Params;
@@ -9,22 +9,14 @@ pub(crate) struct Vars {
     pub(crate) ts: Matrix,
 }
 
-pub(crate) enum VarIndex {
-    E { i_data_point: usize },
-    T { i_data_point: usize, i_trait: usize },
+//  The values of Vars without the metadata, e.g. for checkpoints
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct VarValues {
+    es: Vec<f64>,
+    ts: Matrix,
 }
 
 impl Vars {
-    pub(crate) fn indices(&self) -> impl Iterator<Item=VarIndex> {
-        let n_data_points = self.meta.n_data_points();
-        let n_traits = self.meta.n_traits();
-        (0..n_data_points).flat_map(move |i_data_point| {
-            iter::once(VarIndex::E { i_data_point })
-                .chain((0..n_traits).map(move |i_trait| {
-                    VarIndex::T { i_data_point, i_trait }
-                }))
-        })
-    }
     pub(crate) fn initial_vars(data: &GwasData, params: &Params) -> Vars {
         let meta = data.meta.clone();
         let es = vec![params.mu; data.n_data_points()];
@@ -35,4 +27,13 @@ impl Vars {
             Matrix::fill(data.n_data_points(), data.n_traits(), element_gen);
         Vars { meta, es, ts }
     }
+    pub(crate) fn values(&self) -> VarValues {
+        let es = self.es.clone();
+        let ts = self.ts.clone();
+        VarValues { es, ts }
+    }
+    pub(crate) fn from_values(meta: Meta, values: VarValues) -> Vars {
+        let VarValues { es, ts } = values;
+        Vars { meta, es, ts }
+    }
 }
\ No newline at end of file
@@ -6,24 +6,38 @@ use std::thread::available_parallelism;
 
 use crate::data::{load_data, LoadedData};
 use crate::error::Error;
+use crate::likelihood::log_likelihood;
 use crate::options::action::Action;
-use crate::options::config::{Config, TrainConfig};
-use crate::params::{Params, write_params_to_file};
+use crate::options::config::{CheckpointConfig, Config, TrainConfig};
+use crate::params::{Params, read_params_from_file, write_params_with_seed_to_file};
 use crate::report::Reporter;
 use crate::sample::trace_file::ParamTraceFileWriter;
-use crate::train::initial_params::estimate_initial_params;
+use crate::train::bootstrap::bootstrap;
+use crate::train::checkpoint::{ChainState, Checkpoint, read_checkpoint, write_checkpoint};
+use crate::train::initial_params::{estimate_initial_params, warm_start_params};
+use crate::train::multi_start::fit_multi_start;
 use crate::train::param_meta_stats::ParamMetaStats;
 use crate::train::worker::train_worker;
+use crate::util::rng::seed_or_random;
 use crate::util::threads::{InMessage, OutMessage, Threads, WorkerLauncher};
 
 mod worker;
 pub(crate) mod param_meta_stats;
-mod initial_params;
+pub(crate) mod initial_params;
+pub(crate) mod em;
+mod bootstrap;
+mod checkpoint;
+mod multi_start;
+
+mod defaults {
+    pub(crate) const N_WORKERS_MIN: usize = 3;
+}
 
 #[derive(Clone)]
 pub(crate) enum MessageToWorker {
     TakeNSamples(usize),
     SetNewParams(Params),
+    SendChainStates,
     Shutdown,
 }
 
@@ -31,93 +45,251 @@ impl OutMessage for MessageToWorker {
     const SHUTDOWN: Self = MessageToWorker::Shutdown;
 }
 
-pub(crate) struct MessageToCentral {
-    i_thread: usize,
-    params: Params,
-}
-
-impl MessageToCentral {
-    pub(crate) fn new(i_thread: usize, params: Params) -> MessageToCentral {
-        MessageToCentral { i_thread, params }
-    }
+//  Per chain of the sending thread
+pub(crate) enum MessageToCentral {
+    Params { i_thread: usize, params: Vec<Params> },
+    ChainStates { i_thread: usize, chain_states: Vec<ChainState> },
 }
 
 impl InMessage for MessageToCentral {
-    fn i_thread(&self) -> usize { self.i_thread }
+    fn i_thread(&self) -> usize {
+        match self {
+            MessageToCentral::Params { i_thread, .. } => { *i_thread }
+            MessageToCentral::ChainStates { i_thread, .. } => { *i_thread }
+        }
+    }
 }
 
 #[derive(Clone)]
-struct TrainWorkerLauncher {
+pub(crate) struct TrainWorkerLauncher {
     data: Arc<LoadedData>,
     params: Params,
+    chain_states: Option<Arc<Vec<ChainState>>>,
+    n_steps_burn_in: usize,
+    seed: u64,
+    n_chains: usize,
+    n_threads: usize,
     config: TrainConfig
 }
 
 impl WorkerLauncher<MessageToCentral, MessageToWorker> for TrainWorkerLauncher {
     fn launch(self, in_sender: Sender<MessageToCentral>, out_receiver: Receiver<MessageToWorker>,
               i_thread: usize) {
-        let TrainWorkerLauncher { data, params, config } = self;
-        train_worker(&data, params, in_sender, out_receiver, i_thread, &config);
+        train_worker(self, in_sender, out_receiver, i_thread);
     }
 }
 
-impl TrainWorkerLauncher {
-    fn new(data: Arc<LoadedData>, params: Params, config: TrainConfig) -> TrainWorkerLauncher {
-        TrainWorkerLauncher { data, params, config }
-    }
-}
-
-pub(crate) fn train_or_check(config: &Config, dry: bool) -> Result<(), Error> {
+pub(crate) fn train_or_check(config: &Config, dry: bool, resume_file: Option<&str>)
+                             -> Result<(), Error> {
     let data = load_data(config, Action::Train)?;
     println!("Loaded data for {} variants", data.gwas_data.meta.n_data_points());
     println!("{}", data.gwas_data);
+    let checkpoint =
+        match resume_file {
+            None => { None }
+            Some(resume_file) => {
+                let checkpoint = read_checkpoint(resume_file)?;
+                println!("Resuming from checkpoint {} at round {}, iteration {}.", resume_file,
+                         checkpoint.i_round, checkpoint.i_iteration);
+                Some(checkpoint)
+            }
+        };
     if dry {
         println!("User picked dry run only, so doing nothing.")
     } else {
-        train(data, config)?;
+        train(data, config, checkpoint)?;
     }
     Ok(())
 }
 
-fn train(data: LoadedData, config: &Config) -> Result<(), Error> {
+//  Where a fit starts: its params, its place in the schedule of rounds and, if resumed, the
+//  states of the chains and, in the middle of a round, the meta stats so far.
+struct FitStart {
+    params: Params,
+    i_round: usize,
+    i_iteration: usize,
+    param_meta_stats: Option<ParamMetaStats>,
+    chain_states: Option<Arc<Vec<ChainState>>>,
+    n_steps_burn_in: usize,
+    seed: u64,
+}
+
+impl FitStart {
+    fn new(params: Params, n_steps_burn_in: usize, seed: u64) -> FitStart {
+        FitStart {
+            params, i_round: 0, i_iteration: 0, param_meta_stats: None, chain_states: None,
+            n_steps_burn_in, seed
+        }
+    }
+}
+
+fn train(data: LoadedData, config: &Config, checkpoint: Option<Checkpoint>)
+         -> Result<(), Error> {
     let data = Arc::new(data);
     let n_traits = data.gwas_data.meta.n_traits();
-    let mut params_trace_writer =
-        if let Some(path) = &config.files.trace {
-            let path = PathBuf::from(path);
-            Some(ParamTraceFileWriter::new(path, n_traits)?)
+    let n_starts = config.train.n_starts.unwrap_or(1);
+    if n_starts > 1 && checkpoint.is_some() {
+        Err(Error::from("Resuming is not supported with multiple starts."))?;
+    }
+    //  When resuming, the seed of the checkpoint is used, so chains continue their streams.
+    let saved_seed = checkpoint.as_ref().and_then(|checkpoint| checkpoint.seed);
+    let seed =
+        match (config.train.seed, saved_seed) {
+            (Some(seed), Some(saved_seed)) if seed != saved_seed => {
+                Err(Error::from(format!("Checkpoint was written with seed {}, but seed is {}, \
+                so cannot resume.", saved_seed, seed)))?
+            }
+            (seed, saved_seed) => { seed_or_random(seed.or(saved_seed)) }
+        };
+    println!("Random seed is {}", seed);
+    let start = create_fit_start(&data, config, checkpoint, seed)?;
+    let mut params =
+        if n_starts > 1 {
+            fit_multi_start(&data, config, start, n_starts)?
         } else {
-            None
+            let mut params_trace_writer =
+                if let Some(path) = &config.files.trace {
+                    let path = PathBuf::from(path);
+                    if start.i_round > 0 && path.exists() {
+                        Some(ParamTraceFileWriter::resume(path, start.i_round)?)
+                    } else {
+                        Some(ParamTraceFileWriter::new(path, n_traits)?)
+                    }
+                } else {
+                    None
+                };
+            fit(&data, config, start, &mut params_trace_writer,
+                config.train.checkpoint.as_ref())?
         };
-    let n_threads = cmp::max(available_parallelism()?.get(), 3);
-    println!("Launching {} workers and burning in with {} iterations", n_threads,
-             config.train.n_steps_burn_in);
-    let mut params = estimate_initial_params(&data.gwas_data)?;
+    if config.train.normalize_mu_to_one {
+        params = params.normalized_with_mu_one()
+    }
+    write_params_with_seed_to_file(&params, seed, config.files.params.as_str())?;
+    if let Some(bootstrap_config) = &config.train.bootstrap {
+        bootstrap(&data, &params, bootstrap_config, config.train.normalize_mu_to_one, seed)?;
+    }
+    Ok(())
+}
+
+fn n_workers_default() -> Result<usize, Error> {
+    Ok(cmp::max(available_parallelism()?.get(), defaults::N_WORKERS_MIN))
+}
+
+//  Unless set, one chain per worker thread, as many as there are cores but at least three.
+//  Results then depend on the number of cores, so set n_chains to reproduce them elsewhere.
+fn n_chains(config: &TrainConfig) -> Result<usize, Error> {
+    match config.n_chains {
+        Some(n_chains) => Ok(n_chains),
+        None => n_workers_default(),
+    }
+}
+
+fn create_fit_start(data: &LoadedData, config: &Config, checkpoint: Option<Checkpoint>,
+                    seed: u64) -> Result<FitStart, Error> {
+    let n_steps_burn_in = config.train.n_steps_burn_in;
+    let n_chains = n_chains(&config.train)?;
+    match (checkpoint, &config.train.initial_params_file) {
+        (None, None) => {
+            let params = estimate_initial_params(&data.gwas_data)?;
+            Ok(FitStart::new(params, n_steps_burn_in, seed))
+        }
+        (None, Some(initial_params_file)) => {
+            println!("Starting from params in {}", initial_params_file);
+            let initial_params = read_params_from_file(initial_params_file)?;
+            let params = warm_start_params(&initial_params, &data.gwas_data)?;
+            let n_steps_burn_in =
+                config.train.n_steps_burn_in_warm_start.unwrap_or(n_steps_burn_in);
+            Ok(FitStart::new(params, n_steps_burn_in, seed))
+        }
+        (Some(checkpoint), _) => {
+            let Checkpoint { params, i_round, i_iteration, param_meta_stats, chains, .. } =
+                checkpoint;
+            check_params_match_data(&params, data)?;
+            let mut start = FitStart::new(params, n_steps_burn_in, seed);
+            start.i_round = i_round;
+            match param_meta_stats {
+                Some(param_meta_stats) if param_meta_stats.n_chains_used() != n_chains => {
+                    println!("Checkpoint has {} chains, but config has {}, so restarting round \
+                    {}.", param_meta_stats.n_chains_used(), n_chains, i_round);
+                }
+                param_meta_stats => {
+                    start.i_iteration = i_iteration;
+                    start.param_meta_stats = param_meta_stats;
+                    if chains.len() == n_chains {
+                        start.chain_states = Some(Arc::new(chains));
+                    }
+                }
+            }
+            Ok(start)
+        }
+    }
+}
+
+fn fit(data: &Arc<LoadedData>, config: &Config, start: FitStart,
+       params_trace_writer: &mut Option<ParamTraceFileWriter>,
+       checkpoint_config: Option<&CheckpointConfig>) -> Result<Params, Error> {
+    let FitStart {
+        mut params, mut i_round, mut i_iteration, param_meta_stats, chain_states,
+        n_steps_burn_in, seed
+    } = start;
+    let mut param_meta_stats_resumed = param_meta_stats;
+    let n_chains = n_chains(&config.train)?;
+    let n_threads = cmp::min(n_workers_default()?, n_chains);
+    if chain_states.is_some() {
+        println!("Launching {} workers for {} chains with states from checkpoint", n_threads,
+                 n_chains);
+    } else {
+        println!("Launching {} workers for {} chains and burning in with {} iterations",
+                 n_threads, n_chains, n_steps_burn_in);
+    }
     println!("{}", params);
     let launcher =
-        TrainWorkerLauncher::new(data, params.clone(), config.train.clone());
+        TrainWorkerLauncher {
+            data: data.clone(), params: params.clone(), chain_states, n_steps_burn_in, seed,
+            n_chains, n_threads, config: config.train.clone()
+        };
     let threads =
         Threads::<MessageToCentral, MessageToWorker>::new(launcher, n_threads);
     println!("Workers launched and burned in.");
     let n_samples: usize = config.train.n_samples_per_iteration;
     let mut reporter = Reporter::new();
-    let mut i_round: usize = 0;
-    let mut i_iteration: usize = 0;
     loop {
-        let params0 = create_param_estimates(&threads, n_samples)?;
-        let params1 = create_param_estimates(&threads, n_samples)?;
         let mut param_meta_stats =
-            ParamMetaStats::new(n_threads, params.trait_names.clone(),
-                                &params0, &params1);
+            match param_meta_stats_resumed.take() {
+                Some(param_meta_stats) => { param_meta_stats }
+                None => {
+                    let params0 = create_param_estimates(&threads, n_samples)?;
+                    let params1 = create_param_estimates(&threads, n_samples)?;
+                    ParamMetaStats::new(n_chains, params.trait_names.clone(), &params0,
+                                        &params1)
+                }
+            };
         let mut reached_precision = false;
         loop {
             i_iteration += 1;
             let params_new = create_param_estimates(&threads, n_samples)?;
             param_meta_stats.add(&params_new);
             let summary = param_meta_stats.summary()?;
-            if i_iteration >= config.train.n_iterations_per_round {
+            if i_iteration < config.train.n_iterations_per_round {
+                if let Some(checkpoint_config) = checkpoint_config {
+                    let is_due =
+                        checkpoint_config.n_iterations_between.is_some_and(|n_between| {
+                            n_between > 0 && i_iteration.is_multiple_of(n_between)
+                        });
+                    if is_due {
+                        let chains = collect_chain_states(&threads, checkpoint_config)?;
+                        let checkpoint =
+                            Checkpoint {
+                                params: params.clone(), i_round, i_iteration,
+                                param_meta_stats: Some(param_meta_stats.clone()), chains,
+                                seed: Some(seed)
+                            };
+                        write_checkpoint(&checkpoint, &checkpoint_config.file)?;
+                    }
+                }
+            } else {
                 params = summary.params.clone();
-                if let Some(params_trace_writer) = &mut params_trace_writer {
+                if let Some(params_trace_writer) = params_trace_writer {
                     params_trace_writer.write(&params)?;
                 }
                 if i_round >= config.train.n_rounds {
@@ -131,8 +303,20 @@ fn train(data: LoadedData, config: &Config) -> Result<(), Error> {
                     for sender in threads.out_senders.iter() {
                         sender.send(MessageToWorker::SetNewParams(params.clone()))?;
                     }
+                    if let Some(checkpoint_config) = checkpoint_config {
+                        //  Chain states after the burn-in with the new params
+                        let chains = collect_chain_states(&threads, checkpoint_config)?;
+                        let checkpoint =
+                            Checkpoint {
+                                params: params.clone(), i_round, i_iteration: 0,
+                                param_meta_stats: None, chains, seed: Some(seed)
+                            };
+                        write_checkpoint(&checkpoint, &checkpoint_config.file)?;
+                    }
                 }
-                reporter.report(&summary, i_round, i_iteration, n_samples);
+                let log_likelihood =
+                    log_likelihood(&data.gwas_data, &data.weights, &summary.params);
+                reporter.report(&summary, log_likelihood, i_round, i_iteration, n_samples);
                 reporter.reset_round_timer();
                 break;
             }
@@ -141,18 +325,58 @@ fn train(data: LoadedData, config: &Config) -> Result<(), Error> {
             break;
         }
     };
-    if config.train.normalize_mu_to_one {
-        params = params.normalized_with_mu_one()
-    }
-    write_params_to_file(&params, config.files.params.as_str())?;
-    Ok(())
+    Ok(params)
 }
 
 fn create_param_estimates(threads: &Threads<MessageToCentral, MessageToWorker>, n_samples: usize)
                           -> Result<Vec<Params>, Error> {
     threads.broadcast(MessageToWorker::TakeNSamples(n_samples))?;
     let responses = threads.responses_from_all()?;
-    let params: Vec<Params> =
-        responses.into_iter().map(|response| response.params).collect();
-    Ok(params)
+    let params_by_thread =
+        responses.into_iter().map(|response| {
+            match response {
+                MessageToCentral::Params { params, .. } => { Ok(params) }
+                MessageToCentral::ChainStates { .. } => {
+                    Err(Error::from("Expected params from worker."))
+                }
+            }
+        }).collect::<Result<Vec<Vec<Params>>, Error>>()?;
+    Ok(in_chain_order(params_by_thread))
+}
+
+fn collect_chain_states(threads: &Threads<MessageToCentral, MessageToWorker>,
+                        checkpoint_config: &CheckpointConfig) -> Result<Vec<ChainState>, Error> {
+    threads.broadcast(MessageToWorker::SendChainStates)?;
+    let responses = threads.responses_from_all()?;
+    let chain_states_by_thread =
+        responses.into_iter().map(|response| {
+            match response {
+                MessageToCentral::ChainStates { chain_states, .. } => { Ok(chain_states) }
+                MessageToCentral::Params { .. } => {
+                    Err(Error::from("Expected chain states from worker."))
+                }
+            }
+        }).collect::<Result<Vec<Vec<ChainState>>, Error>>()?;
+    let mut chain_states = in_chain_order(chain_states_by_thread);
+    if !checkpoint_config.include_vars.unwrap_or(true) {
+        chain_states.iter_mut().for_each(|chain_state| chain_state.vars = None);
+    }
+    Ok(chain_states)
+}
+
+fn in_chain_order<T>(by_thread: Vec<Vec<T>>) -> Vec<T> {
+    //  Thread i has chains i, i + n_threads, i + 2 * n_threads and so on
+    let n_threads = by_thread.len();
+    let n_chains: usize = by_thread.iter().map(|values| values.len()).sum();
+    let mut iters: Vec<_> = by_thread.into_iter().map(|values| values.into_iter()).collect();
+    (0..n_chains).filter_map(|i_chain| iters[i_chain % n_threads].next()).collect()
+}
+
+fn check_params_match_data(params: &Params, data: &LoadedData) -> Result<(), Error> {
+    if params.trait_names != data.gwas_data.meta.trait_names {
+        Err(Error::from(format!("Checkpoint is for traits {}, but data has traits {}.",
+                                params.trait_names.join(", "),
+                                data.gwas_data.meta.trait_names.join(", "))))?;
+    }
+    Ok(())
 }
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use rand_distr::{Distribution, Exp1};
+use crate::data::{LoadedData, Weights};
+use crate::error::Error;
+use crate::math::stats::percentile;
+use crate::options::config::{BootstrapConfig, BootstrapMethod};
+use crate::params::{ParamIndex, Params};
+use crate::sample::trace_file::ParamTraceFileWriter;
+use crate::train::em::fit_exact;
+use crate::train::param_meta_stats::str18;
+use crate::util::rng::{new_rng, RngStream};
+
+mod defaults {
+    pub(crate) const N_ITERATIONS: usize = 1000;
+    pub(crate) const CONFIDENCE_LEVEL: f64 = 0.95;
+}
+
+pub(crate) fn bootstrap(data: &LoadedData, params: &Params, config: &BootstrapConfig,
+                        normalize_mu_to_one: bool, seed: u64) -> Result<(), Error> {
+    let n_traits = params.n_traits();
+    let method = config.method.unwrap_or(BootstrapMethod::Resample);
+    let n_iterations = config.n_iterations.unwrap_or(defaults::N_ITERATIONS);
+    let confidence_level = config.confidence_level.unwrap_or(defaults::CONFIDENCE_LEVEL);
+    println!("Bootstrapping {} replicates with {} iterations each.", config.n_replicates,
+             n_iterations);
+    let mut writer =
+        ParamTraceFileWriter::new(PathBuf::from(&config.out_file), n_traits)?;
+    let mut rng = new_rng(seed, RngStream::Bootstrap);
+    let mut values_by_param: Vec<Vec<f64>> =
+        ParamIndex::all(n_traits).map(|_| Vec::with_capacity(config.n_replicates)).collect();
+    for _ in 0..config.n_replicates {
+        let weights = draw_weights(&data.weights, method, &mut rng)?;
+        let mut params_replicate = fit_exact(&data.gwas_data, &weights, params, n_iterations);
+        if normalize_mu_to_one {
+            params_replicate = params_replicate.normalized_with_mu_one();
+        }
+        writer.write(&params_replicate)?;
+        for (i_param, index) in ParamIndex::all(n_traits).enumerate() {
+            values_by_param[i_param].push(params_replicate[index]);
+        }
+    }
+    let p_lower = 0.5 * (1.0 - confidence_level);
+    let p_upper = 1.0 - p_lower;
+    println!("Bootstrap {}% percentile confidence intervals:", 100.0 * confidence_level);
+    println!("{} {} {} {}", str18("param"), str18("value"), str18("lower"), str18("upper"));
+    let params_point = if normalize_mu_to_one { params.normalized_with_mu_one() } else {
+        params.clone()
+    };
+    for (i_param, index) in ParamIndex::all(n_traits).enumerate() {
+        let values = &mut values_by_param[i_param];
+        values.sort_by(f64::total_cmp);
+        println!("{} {} {} {}", str18(index.with_trait_name(&params.trait_names)),
+                 str18(params_point[index]), str18(percentile(values, p_lower)),
+                 str18(percentile(values, p_upper)));
+    }
+    Ok(())
+}
+
+fn draw_weights<R: Rng>(weights: &Weights, method: BootstrapMethod, rng: &mut R)
+                        -> Result<Weights, Error> {
+    let n_data_points = weights.weights.len();
+    let weights_replicate: Vec<f64> =
+        match method {
+            BootstrapMethod::Resample => {
+                let index_distr =
+                    WeightedIndex::new(&weights.weights).map_err(|error| {
+                        Error::from(format!("Cannot resample by weights: {}", error))
+                    })?;
+                let mut counts: Vec<f64> = vec![0.0; n_data_points];
+                for _ in 0..n_data_points {
+                    counts[index_distr.sample(rng)] += 1.0;
+                }
+                counts
+            }
+            BootstrapMethod::Bayesian => {
+                weights.weights.iter().map(|weight| {
+                    let draw: f64 = Exp1.sample(rng);
+                    weight * draw
+                }).collect()
+            }
+        };
+    Ok(Weights::from_vec(weights_replicate))
+}
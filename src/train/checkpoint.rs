@@ -0,0 +1,42 @@
+use std::fs::read_to_string;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, for_file};
+use crate::params::Params;
+use crate::sample::sampler::SamplerState;
+use crate::sample::vars::VarValues;
+use crate::train::param_meta_stats::ParamMetaStats;
+use crate::util::files::write_via_partial;
+use crate::util::rng::SeededRng;
+
+//  State of a training run, enough to continue it. The params are those the workers sample
+//  with in the current round. Without meta stats, the checkpoint is at the start of a round.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Checkpoint {
+    pub(crate) params: Params,
+    pub(crate) i_round: usize,
+    pub(crate) i_iteration: usize,
+    pub(crate) param_meta_stats: Option<ParamMetaStats>,
+    pub(crate) chains: Vec<ChainState>,
+    pub(crate) seed: Option<u64>,
+}
+
+//  Random number generator, sampling stats and, optionally, variables of a chain. A chain
+//  without variables starts them over and burns in.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ChainState {
+    pub(crate) sampler: SamplerState<SeededRng>,
+    pub(crate) vars: Option<VarValues>,
+}
+
+pub(crate) fn write_checkpoint(checkpoint: &Checkpoint, file: &str) -> Result<(), Error> {
+    write_via_partial(file, |writer| {
+        serde_json::to_writer(writer, checkpoint)?;
+        Ok(())
+    })
+}
+
+pub(crate) fn read_checkpoint(file: &str) -> Result<Checkpoint, Error> {
+    let checkpoint_string = for_file(file, read_to_string(file))?;
+    let checkpoint = serde_json::from_str(&checkpoint_string)?;
+    Ok(checkpoint)
+}
@@ -0,0 +1,75 @@
+use crate::data::{GwasData, Weights};
+use crate::params::{ParamIndex, Params};
+use crate::sample::var_stats::VarStats;
+
+const RELATIVE_TOLERANCE: f64 = 1e-9;
+
+pub(crate) fn fit_exact(data: &GwasData, weights: &Weights, params: &Params,
+                        n_iterations_max: usize) -> Params {
+    let n_traits = params.n_traits();
+    let mut params = params.clone();
+    for _ in 0..n_iterations_max {
+        let mut var_stats = VarStats::new(data.meta.clone());
+        var_stats.add_exact(data, &params);
+        let params_new = var_stats.compute_new_params(weights);
+        let converged =
+            ParamIndex::all(n_traits).all(|index| {
+                let value = params[index];
+                let value_new = params_new[index];
+                (value_new - value).abs() <= RELATIVE_TOLERANCE * value.abs().max(1.0)
+            });
+        params = params_new;
+        if converged {
+            break;
+        }
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rand_distr::{Distribution, Normal};
+    use crate::data::{GwasData, Meta, Weights};
+    use crate::math::matrix::Matrix;
+    use crate::params::Params;
+    use crate::train::em::fit_exact;
+
+    #[test]
+    fn recovers_simulated_params() {
+        const N_DATA_POINTS: usize = 5000;
+        let trait_names = Arc::new(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        let params_true =
+            Params { trait_names: trait_names.clone(), mu: 1.0, tau: 0.5,
+                betas: vec![1.0, -2.0, 0.5], sigmas: vec![0.3, 0.2, 0.4] };
+        let se: f64 = 0.1;
+        let mut rng = StdRng::seed_from_u64(42);
+        let standard_normal = Normal::new(0.0, 1.0).unwrap();
+        let mut betas = Matrix::fill(N_DATA_POINTS, 3, |_, _| 0.0);
+        for j in 0..N_DATA_POINTS {
+            let e = params_true.mu + params_true.tau * standard_normal.sample(&mut rng);
+            for i in 0..3 {
+                let t = params_true.betas[i] * e
+                    + params_true.sigmas[i] * standard_normal.sample(&mut rng);
+                betas[j][i] = t + se * standard_normal.sample(&mut rng);
+            }
+        }
+        let ses = Matrix::fill(N_DATA_POINTS, 3, |_, _| se);
+        let var_ids: Vec<String> = (0..N_DATA_POINTS).map(|j| j.to_string()).collect();
+        let meta = Meta::new(trait_names.clone(), Arc::new(var_ids));
+        let data = GwasData { meta, betas, ses };
+        let weights = Weights::from_vec(vec![1.0; N_DATA_POINTS]);
+        let params_start =
+            Params { trait_names, mu: 0.5, tau: 1.0, betas: vec![0.5, -0.5, 0.5],
+                sigmas: vec![1.0, 1.0, 1.0] };
+        let params_fit =
+            fit_exact(&data, &weights, &params_start, 1000).normalized_with_mu_one();
+        assert!((params_fit.tau - params_true.tau).abs() < 0.05);
+        for i in 0..3 {
+            assert!((params_fit.betas[i] - params_true.betas[i]).abs() < 0.05);
+            assert!((params_fit.sigmas[i] - params_true.sigmas[i]).abs() < 0.05);
+        }
+    }
+}
@@ -12,7 +12,7 @@ pub(crate) fn estimate_initial_params(data: &GwasData) -> Result<Params, Error>
     for i_data_point in 0..n_data_points {
         for i_trait in 0..n_traits {
             beta_stats[i_trait].add(data.betas[i_data_point][i_trait]);
-            se_stats[i_trait].add(data.betas[i_data_point][i_trait])
+            se_stats[i_trait].add(data.ses[i_data_point][i_trait])
         }
     }
     let sigmas: Vec<f64> =
@@ -41,4 +41,61 @@ pub(crate) fn estimate_initial_params(data: &GwasData) -> Result<Params, Error>
     let betas: Vec<f64> = beta_means.iter().map(|mean| mean / (mu + tau * mu.signum())).collect();
     let trait_names = meta.trait_names.clone();
     Ok(Params { trait_names, mu, tau, betas, sigmas })
-}
\ No newline at end of file
+}
+
+//  Starts from params of an earlier run, matching traits by name. Traits new to the data get
+//  heuristic beta and sigma, with beta rescaled to keep beta times mu; dropped traits are removed.
+pub(crate) fn warm_start_params(params: &Params, data: &GwasData) -> Result<Params, Error> {
+    let heuristic = estimate_initial_params(data)?;
+    let trait_names = data.meta.trait_names.clone();
+    let mu = params.mu;
+    let tau = params.tau;
+    let mut betas: Vec<f64> = Vec::with_capacity(trait_names.len());
+    let mut sigmas: Vec<f64> = Vec::with_capacity(trait_names.len());
+    let mut traits_new: Vec<String> = Vec::new();
+    for (i_trait, trait_name) in trait_names.iter().enumerate() {
+        match params.trait_names.iter().position(|name| name == trait_name) {
+            Some(i_trait_old) => {
+                betas.push(params.betas[i_trait_old]);
+                sigmas.push(params.sigmas[i_trait_old]);
+            }
+            None => {
+                let beta = heuristic.betas[i_trait];
+                betas.push(if mu == 0.0 { beta } else { beta * heuristic.mu / mu });
+                sigmas.push(heuristic.sigmas[i_trait]);
+                traits_new.push(trait_name.clone());
+            }
+        }
+    }
+    let traits_dropped: Vec<String> =
+        params.trait_names.iter().filter(|name| !trait_names.contains(name)).cloned().collect();
+    if !traits_new.is_empty() {
+        println!("Traits not in initial params, starting from heuristic: {}",
+                 traits_new.join(", "));
+    }
+    if !traits_dropped.is_empty() {
+        println!("Traits of initial params not in data, dropped: {}", traits_dropped.join(", "));
+    }
+    Ok(Params { trait_names, mu, tau, betas, sigmas })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::data::{GwasData, Meta};
+    use crate::math::matrix::Matrix;
+    use crate::train::initial_params::estimate_initial_params;
+
+    #[test]
+    fn tau_from_standard_errors() {
+        //  With one trait, tau is the mean standard error, whatever the betas.
+        let trait_names = Arc::new(vec!["a".to_string()]);
+        let var_ids = Arc::new(vec!["v1".to_string(), "v2".to_string(), "v3".to_string()]);
+        let meta = Meta { trait_names, var_ids };
+        let betas = Matrix::fill(3, 1, |i_data_point, _| (i_data_point + 1) as f64);
+        let ses = Matrix::fill(3, 1, |i_data_point, _| 0.1 * (i_data_point + 1) as f64);
+        let data = GwasData { meta, betas, ses };
+        let params = estimate_initial_params(&data).unwrap();
+        assert!((params.tau - 0.2).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+use rand::Rng;
+use rand_distr::{Distribution, StandardNormal};
+use crate::data::LoadedData;
+use crate::error::Error;
+use crate::likelihood::log_likelihood;
+use crate::options::config::Config;
+use crate::params::{ParamIndex, Params};
+use crate::train::{fit, FitStart};
+use crate::util::rng::{new_rng, RngStream};
+
+mod defaults {
+    pub(crate) const PERTURBATION_SCALE: f64 = 0.5;
+    pub(crate) const CLUSTER_TOLERANCE: f64 = 0.05;
+}
+
+//  Fits from the given start and from randomly perturbed copies of it, and keeps the fit with the
+//  highest log-likelihood. Fits are compared after normalizing to mu one, which maps mirror images
+//  with negated mu and betas, as well as rescalings of E, onto each other.
+pub(crate) fn fit_multi_start(data: &Arc<LoadedData>, config: &Config, start: FitStart,
+                              n_starts: usize) -> Result<Params, Error> {
+    if config.files.trace.is_some() {
+        println!("Not writing a trace file, because training uses multiple starts.");
+    }
+    let params_initial = start.params.clone();
+    let n_steps_burn_in = start.n_steps_burn_in;
+    let mut fits: Vec<Params> = Vec::with_capacity(n_starts);
+    for i_start in 0..n_starts {
+        println!("Start {} of {}", i_start + 1, n_starts);
+        let mut rng = new_rng(start.seed, RngStream::TrainStart(i_start));
+        let seed: u64 = rng.gen();
+        let params =
+            if i_start == 0 {
+                params_initial.clone()
+            } else {
+                perturbed_params(&params_initial, &mut rng)
+            };
+        let start = FitStart::new(params, n_steps_burn_in, seed);
+        fits.push(fit(data, config, start, &mut None, None)?);
+    }
+    let log_likelihoods: Vec<f64> =
+        fits.iter().map(|params| log_likelihood(&data.gwas_data, &data.weights, params))
+            .collect();
+    let fits_normalized: Vec<Params> =
+        fits.iter().map(|params| params.normalized_with_mu_one()).collect();
+    let clusters = cluster_fits(&fits_normalized, &log_likelihoods);
+    let n_clusters = clusters.iter().max().map(|i_cluster| i_cluster + 1).unwrap_or(0);
+    println!("Start\tlog_likelihood\tmu\tsolution");
+    for i_start in 0..n_starts {
+        println!("{}\t{}\t{}\t{}", i_start + 1, log_likelihoods[i_start], fits[i_start].mu,
+                 clusters[i_start] + 1);
+    }
+    let n_flipped = fits.iter().filter(|params| params.mu < 0.0).count();
+    if n_flipped > 0 {
+        println!("{} of {} starts ended with negative mu and were flipped for comparison.",
+                 n_flipped, n_starts);
+    }
+    let i_best =
+        (0..n_starts).max_by(|i, j| log_likelihoods[*i].total_cmp(&log_likelihoods[*j]))
+            .ok_or_else(|| Error::from("Need at least one start."))?;
+    if n_clusters == 1 {
+        println!("All {} starts converged to the same solution.", n_starts);
+    } else {
+        println!("Starts converged to {} different solutions. Keeping start {}, which has the \
+        highest log-likelihood {}.", n_clusters, i_best + 1, log_likelihoods[i_best]);
+    }
+    Ok(sign_aligned(&fits[i_best]))
+}
+
+fn perturbed_params<R: Rng>(params: &Params, rng: &mut R) -> Params {
+    //  Random sign of mu, log-normal factors for tau and sigmas, and betas shifted on the scale
+    //  of their root mean square.
+    let scale = defaults::PERTURBATION_SCALE;
+    let mut normal = || -> f64 { StandardNormal.sample(rng) };
+    let mu = if normal() < 0.0 { -params.mu } else { params.mu };
+    let tau = params.tau * (scale * normal()).exp();
+    let beta_scale =
+        (params.betas.iter().map(|beta| beta.powi(2)).sum::<f64>()
+            / (params.n_traits() as f64)).sqrt();
+    let betas: Vec<f64> =
+        params.betas.iter().map(|beta| beta + scale * beta_scale * normal()).collect();
+    let sigmas: Vec<f64> =
+        params.sigmas.iter().map(|sigma| sigma * (scale * normal()).exp()).collect();
+    let trait_names = params.trait_names.clone();
+    Params { trait_names, mu, tau, betas, sigmas }
+}
+
+fn sign_aligned(params: &Params) -> Params {
+    //  Negating both mu and betas gives the same likelihood
+    let mut params = params.clone();
+    if params.mu < 0.0 {
+        params.mu = -params.mu;
+        params.betas.iter_mut().for_each(|beta| *beta = -*beta);
+    }
+    params
+}
+
+fn cluster_fits(fits_normalized: &[Params], log_likelihoods: &[f64]) -> Vec<usize> {
+    //  Greedy, from the best fit down: each fit joins the first cluster whose best fit is within
+    //  tolerance, or else starts a new one.
+    let mut order: Vec<usize> = (0..fits_normalized.len()).collect();
+    order.sort_by(|i, j| log_likelihoods[*j].total_cmp(&log_likelihoods[*i]));
+    let mut representatives: Vec<usize> = Vec::new();
+    let mut clusters: Vec<usize> = vec![0; fits_normalized.len()];
+    for i_fit in order {
+        let i_cluster =
+            representatives.iter().position(|i_representative| {
+                relative_distance(&fits_normalized[*i_representative], &fits_normalized[i_fit])
+                    < defaults::CLUSTER_TOLERANCE
+            }).unwrap_or_else(|| {
+                representatives.push(i_fit);
+                representatives.len() - 1
+            });
+        clusters[i_fit] = i_cluster;
+    }
+    clusters
+}
+
+fn relative_distance(params1: &Params, params2: &Params) -> f64 {
+    let n_traits = params1.n_traits();
+    let mut diff2_sum: f64 = 0.0;
+    let mut norm1_sum: f64 = 0.0;
+    let mut norm2_sum: f64 = 0.0;
+    for index in ParamIndex::all(n_traits) {
+        diff2_sum += (params1[index] - params2[index]).powi(2);
+        norm1_sum += params1[index].powi(2);
+        norm2_sum += params2[index].powi(2);
+    }
+    diff2_sum.sqrt() / norm1_sum.max(norm2_sum).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::params::Params;
+    use crate::train::multi_start::{cluster_fits, sign_aligned};
+
+    fn params(mu: f64, tau: f64, betas: Vec<f64>) -> Params {
+        let trait_names = Arc::new(vec!["a".to_string(), "b".to_string()]);
+        let sigmas = vec![0.5, 0.5];
+        Params { trait_names, mu, tau, betas, sigmas }
+    }
+
+    #[test]
+    fn mirror_images_cluster_together() {
+        let fit = params(2.0, 1.0, vec![0.5, -1.0]);
+        let mirror = params(-2.0, 1.0, vec![-0.5, 1.0]);
+        let other = params(2.0, 1.0, vec![1.0, 1.0]);
+        let fits_normalized: Vec<Params> =
+            [&fit, &mirror, &other].iter().map(|params| params.normalized_with_mu_one())
+                .collect();
+        let clusters = cluster_fits(&fits_normalized, &[-10.0, -10.0, -20.0]);
+        assert_eq!(clusters, vec![0, 0, 1]);
+        assert_eq!(sign_aligned(&mirror).betas, fit.betas);
+    }
+}
@@ -1,10 +1,12 @@
 use std::fmt::{Display, Formatter};
 use std::sync::Arc;
+use serde::{Deserialize, Serialize};
 use crate::error::Error;
 use crate::math::stats::Stats;
 use crate::math::trident::TridentStats;
 use crate::params::{ParamIndex, Params};
 
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct ParamMetaStats {
     trait_names: Arc<Vec<String>>,
     stats: Vec<Vec<TridentStats>>,
@@ -98,7 +100,7 @@ fn unwrap_or_not_enough_data(value: Option<f64>) -> Result<f64, Error> {
     value.ok_or_else(|| Error::from("Not enough data"))
 }
 
-fn str18<T: Display>(item: T) -> String {
+pub(crate) fn str18<T: Display>(item: T) -> String {
     format!("{}                  ", item)[0..18].to_string()
 }
 
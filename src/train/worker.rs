@@ -1,36 +1,81 @@
-use std::sync::Arc;
 use std::sync::mpsc::{Receiver, Sender};
-use rand::prelude::ThreadRng;
-use rand::thread_rng;
-use crate::data::LoadedData;
-use crate::options::config::TrainConfig;
-use crate::train::{MessageToCentral, MessageToWorker};
-use crate::params::Params;
+use crate::options::config::KernelType;
+use crate::train::{MessageToCentral, MessageToWorker, TrainWorkerLauncher};
+use crate::train::checkpoint::ChainState;
 use crate::sample::sampler::Sampler;
 use crate::sample::vars::Vars;
+use crate::util::rng::{new_rng, RngStream, SeededRng};
 
-pub(crate) fn train_worker(data: &Arc<LoadedData>, mut params: Params,
-                           sender: Sender<MessageToCentral>, receiver: Receiver<MessageToWorker>,
-                           i_thread: usize, config: &TrainConfig) {
-    let mut vars = Vars::initial_vars(&data.gwas_data, &params);
-    let rng = thread_rng();
+struct Chain {
+    sampler: Sampler<SeededRng>,
+    vars: Vars,
+}
+
+pub(crate) fn train_worker(launcher: TrainWorkerLauncher, sender: Sender<MessageToCentral>,
+                           receiver: Receiver<MessageToWorker>, i_thread: usize) {
+    //  Chains are dealt out to threads, so each chain and its random number stream are the same
+    //  whatever the number of threads.
+    let TrainWorkerLauncher {
+        data, mut params, chain_states, n_steps_burn_in, seed, n_chains, n_threads, config
+    } = launcher;
     let meta = data.gwas_data.meta.clone();
-    let mut sampler = Sampler::<ThreadRng>::new(&meta, rng);
-    sampler.sample_n(&data.gwas_data, &params, &mut vars, config.n_steps_burn_in, &mut None);
+    let kernel_type = config.kernel.unwrap_or(KernelType::Gibbs);
+    let rao_blackwell = config.rao_blackwell.unwrap_or(false);
+    let mut chains: Vec<Chain> =
+        (i_thread..n_chains).step_by(n_threads).map(|i_chain| {
+            let chain_state =
+                chain_states.as_ref().and_then(|chain_states| chain_states.get(i_chain));
+            let mut sampler =
+                match chain_state {
+                    Some(chain_state) => {
+                        Sampler::from_state(&meta, kernel_type, chain_state.sampler.clone())
+                    }
+                    None => {
+                        let rng = new_rng(seed, RngStream::TrainChain(i_chain));
+                        Sampler::<SeededRng>::new(&meta, rng, kernel_type, rao_blackwell)
+                    }
+                };
+            let vars =
+                match chain_state.and_then(|chain_state| chain_state.vars.as_ref()) {
+                    Some(var_values) => { Vars::from_values(meta.clone(), var_values.clone()) }
+                    None => {
+                        let mut vars = Vars::initial_vars(&data.gwas_data, &params);
+                        sampler.sample_n(&data.gwas_data, &params, &mut vars, n_steps_burn_in,
+                                         &mut None);
+                        vars
+                    }
+                };
+            Chain { sampler, vars }
+        }).collect();
     loop {
         let in_message = receiver.recv().unwrap();
         match in_message {
             MessageToWorker::TakeNSamples(n_samples) => {
-                sampler.sample_n(&data.gwas_data, &params, &mut vars, n_samples, &mut None);
-                let params_new = sampler.var_stats().compute_new_params(&data.weights);
+                let params_new =
+                    chains.iter_mut().map(|chain| {
+                        chain.sampler.sample_n(&data.gwas_data, &params, &mut chain.vars,
+                                               n_samples, &mut None);
+                        chain.sampler.var_stats().compute_new_params(&data.weights)
+                    }).collect();
                 sender
-                    .send(MessageToCentral::new(i_thread, params_new))
+                    .send(MessageToCentral::Params { i_thread, params: params_new })
                     .unwrap();
             }
+            MessageToWorker::SendChainStates => {
+                let chain_states =
+                    chains.iter().map(|chain| {
+                        ChainState {
+                            sampler: chain.sampler.state(), vars: Some(chain.vars.values())
+                        }
+                    }).collect();
+                sender.send(MessageToCentral::ChainStates { i_thread, chain_states }).unwrap();
+            }
             MessageToWorker::SetNewParams(params_new) => {
                 params = params_new;
-                sampler.sample_n(&data.gwas_data, &params, &mut vars, config.n_steps_burn_in,
-                                 &mut None);
+                for chain in chains.iter_mut() {
+                    chain.sampler.sample_n(&data.gwas_data, &params, &mut chain.vars,
+                                           config.n_steps_burn_in, &mut None);
+                }
             }
             MessageToWorker::Shutdown => {
                 break;
@@ -1,3 +1,4 @@
 pub(crate) mod duration_format;
 pub(crate) mod files;
+pub(crate) mod rng;
 pub(crate) mod threads;
\ No newline at end of file
@@ -1,5 +1,7 @@
+use std::fs::{File, rename};
+use std::io::{BufWriter, Write};
 use std::path::Path;
-use crate::error::Error;
+use crate::error::{Error, for_file};
 
 pub(crate) fn check_parent_dir_exists(path: &str) -> Result<(), Error> {
     if let Some(parent) = Path::new(path).parent() {
@@ -8,4 +10,19 @@ pub(crate) fn check_parent_dir_exists(path: &str) -> Result<(), Error> {
         }
     };
     Ok(())
+}
+
+//  Writes to a file next to the given one and renames it, so a crash never leaves the given file
+//  partial.
+pub(crate) fn write_via_partial<F>(file: &str, write: F) -> Result<(), Error>
+    where F: FnOnce(&mut BufWriter<File>) -> Result<(), Error> {
+    let file_partial = format!("{}_partial", file);
+    {
+        let mut writer =
+            BufWriter::new(for_file(&file_partial, File::create(&file_partial))?);
+        write(&mut writer)?;
+        writer.flush()?;
+    }
+    for_file(file, rename(&file_partial, file))?;
+    Ok(())
 }
\ No newline at end of file